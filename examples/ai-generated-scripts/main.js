@@ -9,8 +9,6 @@
 require('dotenv').config();
 const { NanvixSandbox } = require('hyperlight-nanvix');
 const OpenAI = require('openai');
-const fs = require('fs');
-const path = require('path');
 
 /**
  * Generate JavaScript code using OpenAI
@@ -72,17 +70,10 @@ Requirements:
 
 async function main() {
     const count = parseInt(process.argv[2]) || 1;
-    const tmpDir = '/tmp/hyperlight-nanvix-ai';
-    
-    if (!fs.existsSync(tmpDir)) {
-        fs.mkdirSync(tmpDir, { recursive: true });
-    }
 
     console.log(`Generating and executing ${count} AI-generated script(s)...\n`);
 
     for (let i = 0; i < count; i++) {
-        const scriptPath = path.join(tmpDir, `ai-generated-${i}.js`);
-        
         try {
             console.log(`[${i + 1}/${count}] Generating JavaScript code with AI...`);
             const generatedCode = await generateScript();
@@ -92,17 +83,17 @@ async function main() {
             console.log(generatedCode);
             console.log('-'.repeat(60));
             
-            fs.writeFileSync(scriptPath, generatedCode);
-            
             console.log('Executing in Nanvix sandbox...');
             const sandbox = new NanvixSandbox({
                 logDirectory: '/tmp/hyperlight-nanvix',
                 tmpDirectory: '/tmp/hyperlight-nanvix'
             });
             
-            const result = await sandbox.run(scriptPath);
+            // The sandbox stages the code in its own tmp directory and cleans it up
+            const result = await sandbox.runCode('javascript', generatedCode);
             
             if (result.success) {
+                console.log(result.console);
                 console.log('Execution completed successfully\n');
             } else {
                 console.error('Execution failed:', result.error);
@@ -112,10 +103,6 @@ async function main() {
         } catch (error) {
             console.error('Error:', error.message);
             process.exit(1);
-        } finally {
-            if (fs.existsSync(scriptPath)) {
-                fs.unlinkSync(scriptPath);
-            }
         }
     }
     
@@ -1,50 +1,49 @@
 use anyhow::Result;
-use hyperlight_nanvix::{RuntimeConfig, Sandbox, SyscallAction, SyscallTable};
+use hyperlight_nanvix::{RuntimeConfig, Sandbox, SyscallHooks};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-unsafe fn logging_openat_handler(
-    _state: &(),
-    dirfd: i32,
-    pathname: *const i8,
-    flags: i32,
-    mode: u32,
-) -> i32 {
-    let pathname_str = std::ffi::CStr::from_ptr(pathname)
-        .to_str()
-        .unwrap_or("<invalid>");
-
-    eprintln!(
-        ">>> INTERCEPTED openat: dirfd={}, pathname='{}', flags={}, mode={}",
-        dirfd, pathname_str, flags, mode
-    );
-
-    let result = libc::openat(dirfd, pathname, flags, mode);
-    if result >= 0 {
-        eprintln!(">>> openat SUCCESS: fd={}", result);
-    } else {
-        let errno = *libc::__errno_location();
-        eprintln!(">>> openat FAILED: errno={}", errno);
-    }
-    result
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
     println!("Running guest-examples/file_ops.js with openat syscall logging...");
 
-    let mut syscall_table = SyscallTable::new(());
-    syscall_table.openat = SyscallAction::Forward(logging_openat_handler);
+    // Count the files the guest opens; the counter is shared with the hooks
+    let opened = Arc::new(AtomicUsize::new(0));
+    let hooks =
+        SyscallHooks::with_shared_state(opened.clone()).on_openat(|ctx, pathname, flags, mode| {
+            eprintln!(
+                ">>> INTERCEPTED openat: dirfd={}, pathname={:?}, flags={}, mode={}",
+                ctx.dirfd(),
+                pathname,
+                flags,
+                mode
+            );
+
+            let result = ctx.openat(pathname, flags, mode);
+            match result {
+                Ok(fd) => {
+                    ctx.state().fetch_add(1, Ordering::Relaxed);
+                    eprintln!(">>> openat SUCCESS: fd={}", fd);
+                }
+                Err(errno) => eprintln!(">>> openat FAILED: errno={}", errno),
+            }
+            result
+        });
 
     let config = RuntimeConfig::new()
-        .with_syscall_table(Arc::new(syscall_table))
+        .with_syscall_hooks(hooks)
         .with_log_directory("/tmp/hyperlight-nanvix")
         .with_tmp_directory("/tmp/hyperlight-nanvix");
 
     let mut sandbox = Sandbox::new(config)?;
 
     match sandbox.run("guest-examples/file_ops.js").await {
-        Ok(()) => {
-            println!("Workload completed successfully with syscall interception!");
+        Ok(output) => {
+            print!("{}", output.console);
+            println!(
+                "Workload completed successfully, {} files opened!",
+                opened.load(Ordering::Relaxed)
+            );
         }
         Err(e) => {
             eprintln!("Error: {}", e);
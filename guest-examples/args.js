@@ -0,0 +1,6 @@
+// Prints the command-line arguments passed to the script
+
+console.log("Received " + (scriptArgs.length - 1) + " argument(s)");
+for (let i = 1; i < scriptArgs.length; i++) {
+    console.log("arg[" + i + "]: " + scriptArgs[i]);
+}
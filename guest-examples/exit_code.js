@@ -0,0 +1,6 @@
+// Exits with a non-zero status to exercise exit code propagation
+
+import * as std from 'std';
+
+console.log("Exiting with status 3...");
+std.exit(3);
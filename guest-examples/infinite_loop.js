@@ -0,0 +1,4 @@
+// Never terminates, used to exercise execution timeouts
+
+console.log("Spinning forever...");
+while (true) {}
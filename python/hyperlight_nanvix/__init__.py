@@ -6,7 +6,47 @@ sandbox system, allowing you to run JavaScript, Python, C, and C++
 workloads in isolated environments.
 """
 
-from .hyperlight_nanvix import NanvixSandbox, SandboxConfig, WorkloadResult
+from .hyperlight_nanvix import (
+    NanvixSandbox,
+    SandboxConfig,
+    WorkloadResult,
+    NanvixSandboxPool,
+    PoolConfig,
+    NanvixError,
+    UnknownWorkloadTypeError,
+    WorkloadNotFoundError,
+    InvalidWorkloadError,
+    RegistryUnavailableError,
+    InterpreterMissingError,
+    KernelMissingError,
+    NotCachedError,
+    IntegrityError,
+    GuestCrashedError,
+    GuestExitError,
+    WorkloadTimeoutError,
+    WorkloadCancelledError,
+    LimitExceededError,
+)
 
 __version__ = "0.1.0"
-__all__ = ["NanvixSandbox", "SandboxConfig", "WorkloadResult"]
+__all__ = [
+    "NanvixSandbox",
+    "SandboxConfig",
+    "WorkloadResult",
+    "NanvixSandboxPool",
+    "PoolConfig",
+    "NanvixError",
+    "UnknownWorkloadTypeError",
+    "WorkloadNotFoundError",
+    "InvalidWorkloadError",
+    "RegistryUnavailableError",
+    "InterpreterMissingError",
+    "KernelMissingError",
+    "NotCachedError",
+    "IntegrityError",
+    "GuestCrashedError",
+    "GuestExitError",
+    "WorkloadTimeoutError",
+    "WorkloadCancelledError",
+    "LimitExceededError",
+]
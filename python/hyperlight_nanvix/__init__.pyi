@@ -1,17 +1,85 @@
-from typing import Optional
+from typing import List, Optional
 
 class SandboxConfig:
     log_directory: Optional[str]
     tmp_directory: Optional[str]
-    def __init__(self, log_directory: Optional[str] = None, tmp_directory: Optional[str] = None) -> None: ...
+    timeout: Optional[float]
+    offline: Optional[bool]
+    cache_directory: Optional[str]
+    digest_file: Optional[str]
+    process_memory_limit: Optional[int]
+    process_cpu_time_limit: Optional[float]
+    output_limit: Optional[int]
+    policy_file: Optional[str]
+    audit_file: Optional[str]
+    def __init__(self, log_directory: Optional[str] = None, tmp_directory: Optional[str] = None, timeout: Optional[float] = None, offline: Optional[bool] = None, cache_directory: Optional[str] = None, process_memory_limit: Optional[int] = None, process_cpu_time_limit: Optional[float] = None, output_limit: Optional[int] = None, policy_file: Optional[str] = None, audit_file: Optional[str] = None, digest_file: Optional[str] = None) -> None: ...
+
+class NanvixError(Exception): ...
+class UnknownWorkloadTypeError(NanvixError): ...
+class WorkloadNotFoundError(NanvixError): ...
+class InvalidWorkloadError(NanvixError): ...
+class RegistryUnavailableError(NanvixError): ...
+class InterpreterMissingError(NanvixError): ...
+class KernelMissingError(NanvixError): ...
+class NotCachedError(NanvixError): ...
+class IntegrityError(NanvixError): ...
+class GuestCrashedError(NanvixError): ...
+class GuestExitError(NanvixError): ...
+class WorkloadTimeoutError(NanvixError): ...
+class WorkloadCancelledError(NanvixError): ...
+class LimitExceededError(NanvixError): ...
 
 class WorkloadResult:
     success: bool
     error: Optional[str]
+    console: str
+    exit_code: Optional[int]
+    duration: float
+    def check_returncode(self) -> None: ...
 
 class NanvixSandbox:
     def __init__(self, config: Optional[SandboxConfig] = None) -> None: ...
-    async def run(self, workload_path: str) -> WorkloadResult: ...
+    async def run(self, workload_path: str, args: Optional[List[str]] = None) -> WorkloadResult: ...
+    async def run_code(self, workload_type: str, code: str) -> WorkloadResult: ...
+    def cancel(self) -> None: ...
+    async def snapshot(self, workload_type: str) -> str: ...
     async def clear_cache(self) -> bool: ...
 
-__all__ = ["NanvixSandbox", "SandboxConfig", "WorkloadResult"]
+class PoolConfig:
+    size: Optional[int]
+    max_reuse: Optional[int]
+    idle_timeout: Optional[float]
+    workload_types: Optional[List[str]]
+    def __init__(self, size: Optional[int] = None, max_reuse: Optional[int] = None, idle_timeout: Optional[float] = None, workload_types: Optional[List[str]] = None) -> None: ...
+
+class NanvixSandboxPool:
+    idle_count: int
+    def __init__(self, config: Optional[SandboxConfig] = None, pool_config: Optional[PoolConfig] = None) -> None: ...
+    async def warm(self) -> None: ...
+    async def run(self, workload_path: str, args: Optional[List[str]] = None) -> WorkloadResult: ...
+    async def run_code(self, workload_type: str, code: str) -> WorkloadResult: ...
+    def cancel(self) -> None: ...
+    def evict_idle(self) -> int: ...
+    def shutdown(self) -> None: ...
+
+__all__ = [
+    "NanvixSandbox",
+    "SandboxConfig",
+    "WorkloadResult",
+    "NanvixSandboxPool",
+    "PoolConfig",
+    "NanvixError",
+    "UnknownWorkloadTypeError",
+    "WorkloadNotFoundError",
+    "InvalidWorkloadError",
+    "RegistryUnavailableError",
+    "InterpreterMissingError",
+    "KernelMissingError",
+    "NotCachedError",
+    "IntegrityError",
+    "GuestCrashedError",
+    "GuestExitError",
+    "WorkloadTimeoutError",
+    "WorkloadCancelledError",
+    "LimitExceededError",
+]
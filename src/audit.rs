@@ -0,0 +1,189 @@
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nanvix::log;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+use crate::policy::errno_name;
+
+/// `openat` flags decoded by name in audit records
+const OPEN_FLAG_NAMES: &[(&str, i32)] = &[
+    ("O_CREAT", libc::O_CREAT),
+    ("O_EXCL", libc::O_EXCL),
+    ("O_NOCTTY", libc::O_NOCTTY),
+    ("O_TRUNC", libc::O_TRUNC),
+    ("O_APPEND", libc::O_APPEND),
+    ("O_NONBLOCK", libc::O_NONBLOCK),
+    ("O_DIRECTORY", libc::O_DIRECTORY),
+    ("O_NOFOLLOW", libc::O_NOFOLLOW),
+    ("O_CLOEXEC", libc::O_CLOEXEC),
+];
+
+/// A guest syscall as recorded in the audit trail.
+///
+/// Serialized as one JSON object per line, e.g.
+///
+/// ```json
+/// {"timestamp_us":1760000000000000,"syscall":"openat","args":{"dirfd":"AT_FDCWD","pathname":"/tmp/test_file.txt","flags":"O_WRONLY|O_CREAT|O_TRUNC","mode":420},"result":3,"host_path":"/tmp/test_file.txt"}
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyscallEvent {
+    /// When the guest issued the syscall, in microseconds since the Unix epoch
+    pub timestamp_us: u64,
+    /// Name of the syscall, e.g. `"openat"`
+    pub syscall: String,
+    /// Decoded arguments, with paths as strings
+    pub args: Map<String, Value>,
+    /// Value returned to the guest, `-1` on failure
+    pub result: i64,
+    /// Error number the syscall failed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errno: Option<i32>,
+    /// Name of the error number, e.g. `"ENOENT"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Host path a path argument resolved to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_path: Option<String>,
+}
+
+impl SyscallEvent {
+    pub fn new<S: Into<String>>(
+        timestamp: SystemTime,
+        syscall: S,
+        args: Map<String, Value>,
+        result: i64,
+        errno: Option<i32>,
+    ) -> Self {
+        let timestamp_us = timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_micros() as u64)
+            .unwrap_or(0);
+        Self {
+            timestamp_us,
+            syscall: syscall.into(),
+            args,
+            result,
+            errno,
+            error: errno.and_then(errno_name).map(str::to_string),
+            host_path: None,
+        }
+    }
+
+    /// Record the host path a path argument resolved to
+    pub fn with_host_path(mut self, host_path: Option<&Path>) -> Self {
+        self.host_path = host_path.map(|path| path.to_string_lossy().into_owned());
+        self
+    }
+}
+
+/// Decode the arguments of an `openat` call
+pub(crate) fn openat_args(
+    dirfd: i32,
+    pathname: &Path,
+    flags: i32,
+    mode: u32,
+) -> Map<String, Value> {
+    let mut args = Map::new();
+    args.insert(
+        "dirfd".to_string(),
+        if dirfd == libc::AT_FDCWD {
+            Value::from("AT_FDCWD")
+        } else {
+            Value::from(dirfd)
+        },
+    );
+    args.insert(
+        "pathname".to_string(),
+        Value::from(pathname.to_string_lossy().into_owned()),
+    );
+    args.insert("flags".to_string(), Value::from(open_flag_names(flags)));
+    args.insert("mode".to_string(), Value::from(mode));
+    args
+}
+
+/// Render `openat` flags as `O_WRONLY|O_CREAT|...`
+fn open_flag_names(flags: i32) -> String {
+    let mut names = vec![match flags & libc::O_ACCMODE {
+        libc::O_WRONLY => "O_WRONLY".to_string(),
+        libc::O_RDWR => "O_RDWR".to_string(),
+        _ => "O_RDONLY".to_string(),
+    }];
+    let mut rest = flags & !libc::O_ACCMODE;
+    for (name, flag) in OPEN_FLAG_NAMES {
+        if rest & flag == *flag {
+            names.push(name.to_string());
+            rest &= !flag;
+        }
+    }
+    if rest != 0 {
+        names.push(format!("{:#o}", rest));
+    }
+    names.join("|")
+}
+
+/// Receives an event for every guest syscall the host intercepts, which is
+/// `openat` alone with this nanvix release.
+///
+/// Sinks are called synchronously from the syscall path of the guest, so
+/// they should be quick. Closures taking a `&SyscallEvent` are sinks too.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &SyscallEvent);
+}
+
+impl<F: Fn(&SyscallEvent) + Send + Sync> AuditSink for F {
+    fn record(&self, event: &SyscallEvent) {
+        self(event)
+    }
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for Arc<S> {
+    fn record(&self, event: &SyscallEvent) {
+        (**self).record(event)
+    }
+}
+
+/// Writes every event as a line of JSON
+pub struct JsonLinesSink<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl JsonLinesSink<LineWriter<File>> {
+    /// Append events to a file, creating it if needed.
+    ///
+    /// Every line is flushed as it is written, so the trail is complete even
+    /// if the host process is killed.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(LineWriter::new(file)))
+    }
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<W: Write + Send> AuditSink for JsonLinesSink<W> {
+    fn record(&self, event: &SyscallEvent) {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let written = serde_json::to_writer(&mut *writer, event)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"));
+        if let Err(e) = written {
+            log::warn!("Failed to write syscall audit record: {}", e);
+        }
+    }
+}
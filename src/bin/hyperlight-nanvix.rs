@@ -1,55 +1,239 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use hyperlight_nanvix::{cache, RuntimeConfig, Sandbox};
+use hyperlight_nanvix::cache::{Cache, Release, ReleaseInfo};
+use hyperlight_nanvix::integrity::DigestStore;
+use hyperlight_nanvix::runtime::{env_flag_enabled, OFFLINE_ENV_VAR};
+use hyperlight_nanvix::snapshot::SnapshotStore;
+use hyperlight_nanvix::{
+    Error, Fault, Flavor, JsonLinesSink, RuntimeConfig, Sandbox, SyscallPolicy, SyscallTrace,
+    WorkloadType,
+};
 use nanvix::log;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 /// A Hyperlight VMM wrapper with out-of-the-box support for running Nanvix microkernel guests
 #[derive(Parser)]
 #[command(name = "hyperlight-nanvix")]
 #[command(about = "Run scripts in a Nanvix microkernel guest")]
 #[command(
-    after_help = "Supported file types: .js, .mjs (JavaScript), .py (Python), .elf, .o (Binary)"
+    after_help = "Supported workloads: .js, .mjs or #!/usr/bin/env qjs (JavaScript), .py or #!/usr/bin/env python3 (Python), i686 Nanvix ELF executables (Binary)"
 )]
 struct Cli {
     /// Show detailed nanvix logging
     #[arg(long)]
     verbose: bool,
 
+    /// Abort the workload if it runs longer than this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Abort the workload if this process allocates more memory than this while it
+    /// runs, e.g. 512M. Measured for the whole host process, not just the guest
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    process_memory_limit: Option<u64>,
+
+    /// Abort the workload if this process consumes more than this many seconds of CPU
+    /// time while it runs. Measured for the whole host process, not just the guest
+    #[arg(long, value_name = "SECONDS")]
+    process_cpu_time_limit: Option<u64>,
+
+    /// Abort the workload if it writes more output than this, e.g. 1M
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    output_limit: Option<u64>,
+
+    /// Never download from the network registry (also enabled by HYPERLIGHT_NANVIX_OFFLINE=1)
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Directory holding the nanvix-registry cache (defaults to ~/.cache/nanvix-registry)
+    #[arg(long, global = true, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// File holding the digests of cached binaries, e.g. one shipped with a read-only cache
+    /// (defaults to ~/.local/share/hyperlight-nanvix/cache-digests.toml)
+    #[arg(long, global = true, value_name = "FILE")]
+    digest_file: Option<PathBuf>,
+
+    /// Registry machine/deployment flavor. nanvix is built with single-process
+    /// support only, so running a guest with a multi-process or standalone flavor
+    /// fails; those flavors can still be selected for the cache commands
+    #[arg(long, global = true, value_name = "MACHINE/DEPLOYMENT", default_value_t = Flavor::default())]
+    flavor: Flavor,
+
+    /// Pin the registry release providing the kernel and interpreters, e.g. 0.4.x
+    #[arg(long, value_name = "VERSION")]
+    release_version: Option<String>,
+
+    /// Use the exact releases recorded in this lockfile, recording new ones
+    #[arg(long, value_name = "PATH")]
+    lockfile: Option<PathBuf>,
+
+    /// Restrict guest syscalls with a TOML or JSON policy file
+    #[arg(long, value_name = "FILE")]
+    policy: Option<PathBuf>,
+
+    /// Append a JSON line for every guest openat to this file (the only
+    /// syscall this nanvix release intercepts)
+    #[arg(long, value_name = "FILE")]
+    audit: Option<PathBuf>,
+
+    /// Record the run's openat calls and the files they opened to a trace for
+    /// `replay`; no other syscall is recorded with this nanvix release
+    #[arg(long)]
+    record: bool,
+
+    /// Fail a guest syscall with an error, e.g. openat:ENOENT:0.1, openat:EIO:every=3
+    /// or openat:EACCES:path=/data/* (repeatable). Only openat can fail with this
+    /// nanvix release; read, write and other syscalls are rejected
+    #[arg(long = "inject-fault", value_name = "SYSCALL:ERRNO[:TRIGGER]")]
+    faults: Vec<Fault>,
+
+    /// Seed for probabilistic faults, so runs are reproducible
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    fault_seed: u64,
+
     #[command(subcommand)]
     command: Option<Commands>,
 
     /// Path to the script to run
     #[arg(value_name = "SCRIPT")]
     script_path: Option<PathBuf>,
+
+    /// Arguments passed through to the guest workload
+    #[arg(value_name = "ARGS", last = true)]
+    args: Vec<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Download nanvix registry and show compilation instructions
-    SetupRegistry,
+    SetupRegistry {
+        /// Trust the binaries already in the cache that have no recorded digest,
+        /// e.g. ones cached by an older release or by hand; check the cache first
+        #[arg(long)]
+        trust_existing: bool,
+    },
     /// Clear the nanvix registry cache
     ClearRegistry,
+    /// Inspect and manage cached registry releases
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Take and manage guest snapshots for fast starts
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+    /// Re-run a workload with the openat outcomes recorded by `--record`.
+    /// Only openat results and the contents of the files it opened are reproduced;
+    /// clock reads, reads and writes of open files and other syscalls are not
+    Replay {
+        /// Trace file written by a recorded run
+        trace: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotCommands {
+    /// Boot a guest, initialise its interpreter and persist a snapshot of it
+    Create {
+        /// Workload type to snapshot: javascript or python
+        workload_type: WorkloadType,
+    },
+    /// List persisted snapshots
+    List,
+    /// Remove every persisted snapshot
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// List every cached release with its flavor, binaries, size and last use
+    List,
+    /// Show the details of a cached release
+    Info {
+        /// Release directory name, as shown by `cache list`
+        release: String,
+    },
+    /// Remove all but the N most recently used releases of every flavor
+    Prune {
+        #[arg(long, value_name = "N")]
+        keep: usize,
+    },
+    /// Remove a cached release
+    Remove {
+        /// Release directory name, as shown by `cache list`
+        release: String,
+    },
+    /// Unpack a <machine>-<deployment>-<version> release archive into the cache
+    Import {
+        /// Path to the .tar.gz release archive
+        archive: PathBuf,
+    },
+    /// Pack cached releases into <DIR>/<release>.tar.gz archives
+    Export {
+        /// Directory to write the archives to
+        dir: PathBuf,
+        /// Only export this release (defaults to every release)
+        #[arg(long, value_name = "RELEASE")]
+        release: Option<String>,
+    },
+}
+
+/// Parse a size in bytes with an optional K, M or G (binary) suffix
+fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    let (digits, shift) = match value.to_ascii_uppercase().chars().last() {
+        Some('K') => (&value[..value.len() - 1], 10),
+        Some('M') => (&value[..value.len() - 1], 20),
+        Some('G') => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|size| size.checked_mul(1 << shift))
+        .ok_or_else(|| format!("expected a size such as 4096, 64K or 512M, got {:?}", value))
 }
 
 /// Default log-level (overridden by RUST_LOG environment variable if set).
 const DEFAULT_LOG_LEVEL: &str = "info";
 
-async fn setup_registry_command() -> Result<()> {
+/// Log and temporary directory of every command, traces and staged files included
+const WORK_DIRECTORY: &str = "/tmp/hyperlight-nanvix";
+
+async fn setup_registry_command(cache: &Cache, offline: bool, trust_existing: bool) -> Result<()> {
     println!("Setting up Nanvix registry...");
 
     // Check cache status first using local filesystem probes
-    let kernel_cached = cache::is_binary_cached("kernel.elf");
-    let qjs_cached = cache::is_binary_cached("qjs");
-    let python_cached = cache::is_binary_cached("python3");
+    let kernel_cached = cache.is_binary_cached("kernel.elf");
+    let qjs_cached = cache.is_binary_cached("qjs");
+    let python_cached = cache.is_binary_cached("python3");
 
     if kernel_cached && qjs_cached && python_cached {
-        println!("Registry already set up at ~/.cache/nanvix-registry/");
+        println!("Registry already set up at {}", cache.root().display());
+    } else if offline {
+        let missing: Vec<&str> = [
+            ("kernel.elf", kernel_cached),
+            ("qjs", qjs_cached),
+            ("python3", python_cached),
+        ]
+        .iter()
+        .filter(|(_, cached)| !cached)
+        .map(|(name, _)| *name)
+        .collect();
+        anyhow::bail!(
+            "Offline mode is enabled but {} not cached",
+            missing.join(", ")
+        );
     } else {
         // Download missing binaries via get_cached_binary_path (local first, registry fallback)
         if !kernel_cached {
             print!("Downloading kernel.elf... ");
-            cache::get_cached_binary_path("kernel.elf")
+            cache
+                .get_cached_binary_path("kernel.elf")
                 .await
                 .ok_or_else(|| anyhow::anyhow!("Failed to download kernel.elf"))?;
             println!("done");
@@ -59,7 +243,8 @@ async fn setup_registry_command() -> Result<()> {
 
         if !qjs_cached {
             print!("Downloading qjs binary... ");
-            cache::get_cached_binary_path("qjs")
+            cache
+                .get_cached_binary_path("qjs")
                 .await
                 .ok_or_else(|| anyhow::anyhow!("Failed to download qjs"))?;
             println!("done");
@@ -69,7 +254,8 @@ async fn setup_registry_command() -> Result<()> {
 
         if !python_cached {
             print!("Downloading python3 binary... ");
-            cache::get_cached_binary_path("python3")
+            cache
+                .get_cached_binary_path("python3")
                 .await
                 .ok_or_else(|| anyhow::anyhow!("Failed to download python3"))?;
             println!("done");
@@ -77,8 +263,22 @@ async fn setup_registry_command() -> Result<()> {
             println!("python3 already cached");
         }
 
-        println!("\nRegistry setup complete at ~/.cache/nanvix-registry/");
+        println!("\nRegistry setup complete at {}", cache.root().display());
+    }
+
+    if trust_existing {
+        for path in cache.trust_existing()? {
+            println!("Trusted {}", path.display());
+        }
+    }
+
+    // Binaries cached without a recorded digest are refused unless trusted above
+    for name in ["kernel.elf", "qjs", "python3"] {
+        if let Some(binary) = cache.find_release(name, &Release::Latest) {
+            cache.verify(&binary)?;
+        }
     }
+    println!("Verified binary digests");
 
     println!("\nTo compile and run C/C++ programs, see the README:");
     println!(
@@ -88,11 +288,10 @@ async fn setup_registry_command() -> Result<()> {
     Ok(())
 }
 
-async fn clear_registry_command() -> Result<()> {
+async fn clear_registry_command(config: RuntimeConfig) -> Result<()> {
     println!("Clearing Nanvix registry cache...");
 
-    // Create a minimal config to instantiate the Sandbox for cache clearing
-    let config = RuntimeConfig::new();
+    // Instantiate the Sandbox for cache clearing
     let sandbox = Sandbox::new(config)?;
 
     match sandbox.clear_cache().await {
@@ -107,24 +306,199 @@ async fn clear_registry_command() -> Result<()> {
     Ok(())
 }
 
+async fn snapshot_command(config: RuntimeConfig, command: SnapshotCommands) -> Result<()> {
+    match command {
+        SnapshotCommands::Create { workload_type } => {
+            let sandbox = Sandbox::new(config)?;
+            let path = sandbox.snapshot(workload_type).await?;
+            println!("Saved snapshot to {}", path.display());
+        }
+        SnapshotCommands::List => {
+            let store = SnapshotStore::default();
+            let snapshots = store.list()?;
+            if snapshots.is_empty() {
+                println!("No snapshots at {}", store.directory().display());
+            }
+            for snapshot in snapshots {
+                let size = std::fs::metadata(&snapshot)?.len();
+                println!("{} ({})", snapshot.display(), format_size(size));
+            }
+        }
+        SnapshotCommands::Clear => {
+            let removed = Sandbox::new(config)?.clear_snapshots()?;
+            println!("Removed {} snapshot(s)", removed);
+        }
+    }
+    Ok(())
+}
+
+/// Format a size in bytes using binary units
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Format the last use of a release relative to now
+fn format_last_used(last_used: Option<SystemTime>) -> String {
+    let Some(last_used) = last_used else {
+        return "never".to_string();
+    };
+    let seconds = SystemTime::now()
+        .duration_since(last_used)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", seconds / 60),
+        3600..=86399 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+fn format_flavor(info: &ReleaseInfo) -> String {
+    info.flavor
+        .as_ref()
+        .map(|flavor| flavor.to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn cache_command(cache: &Cache, command: CacheCommands) -> Result<()> {
+    match command {
+        CacheCommands::List => {
+            let releases = cache.list_releases()?;
+            if releases.is_empty() {
+                println!("No releases cached at {}", cache.root().display());
+                return Ok(());
+            }
+            println!(
+                "{:<40} {:<28} {:>10}  {:<14} BINARIES",
+                "RELEASE", "FLAVOR", "SIZE", "LAST USED"
+            );
+            for info in &releases {
+                println!(
+                    "{:<40} {:<28} {:>10}  {:<14} {}",
+                    info.name,
+                    format_flavor(info),
+                    format_size(info.size),
+                    format_last_used(info.last_used),
+                    info.binaries.join(", ")
+                );
+            }
+        }
+        CacheCommands::Info { release } => {
+            let info = cache.release_info(&release)?;
+            println!("Release:   {}", info.release);
+            println!("Flavor:    {}", format_flavor(&info));
+            println!("Path:      {}", info.path.display());
+            println!("Size:      {}", format_size(info.size));
+            println!("Last used: {}", format_last_used(info.last_used));
+            println!("Binaries:");
+            for binary in &info.binaries {
+                println!("  {}", binary);
+            }
+        }
+        CacheCommands::Prune { keep } => {
+            let removed = cache.prune(keep)?;
+            for info in &removed {
+                println!("Removed {} ({})", info.name, format_size(info.size));
+            }
+            let freed: u64 = removed.iter().map(|info| info.size).sum();
+            println!(
+                "Pruned {} release(s), freed {}",
+                removed.len(),
+                format_size(freed)
+            );
+        }
+        CacheCommands::Remove { release } => {
+            let info = cache.remove_release(&release)?;
+            println!("Removed {} ({})", info.name, format_size(info.size));
+        }
+        CacheCommands::Import { archive } => {
+            let info = cache.import_release(&archive)?;
+            println!(
+                "Imported {} ({}) with {}",
+                info.name,
+                format_flavor(&info),
+                info.binaries.join(", ")
+            );
+        }
+        CacheCommands::Export { dir, release } => {
+            let names = match release {
+                Some(name) => vec![name],
+                None => cache
+                    .list_releases()?
+                    .into_iter()
+                    .map(|info| info.name)
+                    .collect(),
+            };
+            for name in names {
+                let archive = cache.export_release(&name, &dir)?;
+                println!("Exported {} to {}", name, archive.display());
+            }
+        }
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // The --offline flag forces offline mode, otherwise the environment decides
+    let offline =
+        cli.offline || std::env::var(OFFLINE_ENV_VAR).is_ok_and(|value| env_flag_enabled(&value));
+
+    let mut cache = cli
+        .cache_dir
+        .clone()
+        .map(Cache::new)
+        .unwrap_or_default()
+        .with_flavor(cli.flavor.clone());
+    let mut base_config = RuntimeConfig::new()
+        .with_flavor(cli.flavor)
+        .with_offline(offline)
+        .with_log_directory(WORK_DIRECTORY)
+        .with_tmp_directory(WORK_DIRECTORY);
+    if let Some(cache_dir) = &cli.cache_dir {
+        base_config = base_config.with_cache_directory(cache_dir.to_string_lossy());
+    }
+    if let Some(digest_file) = &cli.digest_file {
+        cache = cache.with_digest_store(DigestStore::new(digest_file));
+        base_config = base_config.with_digest_file(digest_file.to_string_lossy());
+    }
+
     // Handle subcommands
     if let Some(command) = cli.command {
         return match command {
-            Commands::SetupRegistry => setup_registry_command().await,
-            Commands::ClearRegistry => clear_registry_command().await,
+            Commands::SetupRegistry { trust_existing } => {
+                setup_registry_command(&cache, offline, trust_existing).await
+            }
+            Commands::ClearRegistry => clear_registry_command(base_config).await,
+            Commands::Cache { command } => cache_command(&cache, command),
+            Commands::Snapshot { command } => snapshot_command(base_config, command).await,
+            Commands::Replay { trace } => replay_command(base_config, &trace).await,
         };
     }
 
     // Require script path for default operation
     let script_path = cli.script_path.unwrap_or_else(|| {
         eprintln!("error: the following required arguments were not provided:\n  <SCRIPT>\n");
-        eprintln!("Usage: hyperlight-nanvix [OPTIONS] <SCRIPT>");
+        eprintln!("Usage: hyperlight-nanvix [OPTIONS] <SCRIPT> [-- <ARGS>...]");
         eprintln!("       hyperlight-nanvix setup-registry");
         eprintln!("       hyperlight-nanvix clear-registry");
+        eprintln!("       hyperlight-nanvix cache <list|info|prune|remove|import|export>");
+        eprintln!("       hyperlight-nanvix snapshot <create|list|clear>");
+        eprintln!("       hyperlight-nanvix replay <TRACE>");
         eprintln!("\nFor more information, try '--help'.");
         std::process::exit(1);
     });
@@ -140,27 +514,104 @@ async fn main() -> Result<()> {
         log::init(
             false,
             DEFAULT_LOG_LEVEL,
-            "/tmp/hyperlight-nanvix".to_string(),
+            base_config.log_directory.clone(),
             None,
         );
     }
 
     // Create runtime configuration
-    let config = RuntimeConfig::new()
-        .with_log_directory("/tmp/hyperlight-nanvix")
-        .with_tmp_directory("/tmp/hyperlight-nanvix");
+    let mut config = base_config;
+    if let Some(seconds) = cli.timeout {
+        config = config.with_timeout(Duration::from_secs(seconds));
+    }
+    if let Some(bytes) = cli.process_memory_limit {
+        config = config.with_process_memory_limit(bytes);
+    }
+    if let Some(seconds) = cli.process_cpu_time_limit {
+        config = config.with_process_cpu_time_limit(Duration::from_secs(seconds));
+    }
+    if let Some(bytes) = cli.output_limit {
+        config = config.with_output_limit(bytes);
+    }
+    if let Some(version) = cli.release_version {
+        config = config.with_release_version(version);
+    }
+    if let Some(lockfile) = &cli.lockfile {
+        config = config.with_lockfile(lockfile.to_string_lossy());
+    }
+    if let Some(policy) = &cli.policy {
+        config = config.with_syscall_policy(SyscallPolicy::from_file(policy)?);
+    }
+    if let Some(audit) = &cli.audit {
+        config = config.with_syscall_audit(JsonLinesSink::create(audit)?);
+    }
+    config = config.with_syscall_recording(cli.record);
+    for fault in cli.faults {
+        config = config.with_fault(fault);
+    }
+    config = config.with_fault_seed(cli.fault_seed);
 
+    let code = run_workload(config, &script_path, &cli.args).await?;
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// Run a workload and print its output, returning the exit code for the CLI
+async fn run_workload(config: RuntimeConfig, script_path: &Path, args: &[String]) -> Result<i32> {
     // Create Sandbox instance
     let mut sandbox = Sandbox::new(config)?;
 
     // Run the workload
-    match sandbox.run(&script_path).await {
-        Ok(()) => {}
+    match sandbox.run_with_args(script_path, args).await {
+        Ok(output) => {
+            print!("{}", output.console);
+            if let Some(trace) = &output.syscall_trace {
+                eprintln!("Syscall trace written to {}", trace.display());
+            }
+
+            // The pinned nanvix does not report the guest's exit code, so a
+            // completed run exits with 0 whatever the guest did
+            std::io::stdout().flush()?;
+            Ok(output.exit_status.code().unwrap_or(0))
+        }
         Err(e) => {
             eprintln!("Error running workload: {}", e);
-            std::process::exit(1);
+            // Use the same exit code as timeout(1) so scripts can tell timeouts apart
+            if let Error::Timeout(_) = e {
+                return Ok(124);
+            }
+            Ok(1)
         }
     }
+}
 
-    Ok(())
+async fn replay_command(config: RuntimeConfig, trace_path: &Path) -> Result<()> {
+    let trace = SyscallTrace::load(trace_path)?;
+
+    // Stage the recorded workload under its original file name, so its type is detected alike
+    let file_name = trace
+        .header
+        .workload_path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("trace does not name a workload file"))?;
+    let staging = Path::new(&config.tmp_directory).join(format!("replay-{}", std::process::id()));
+    std::fs::create_dir_all(&staging)?;
+    let script_path = staging.join(file_name);
+    std::fs::write(&script_path, &trace.workload)?;
+
+    eprintln!(
+        "Replaying {} ({} syscalls)",
+        trace.header.workload_path.display(),
+        trace.records.len()
+    );
+    let args = trace.header.args.clone();
+    let config = config.with_syscall_replay(trace);
+    let code = run_workload(config, &script_path, &args).await;
+    std::fs::remove_dir_all(&staging)?;
+    match code? {
+        0 => Ok(()),
+        code => std::process::exit(code),
+    }
 }
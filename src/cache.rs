@@ -1,69 +1,915 @@
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
 
 use nanvix::registry::Registry;
 
+use crate::integrity::DigestStore;
+use crate::runtime::{WorkloadType, KERNEL_BINARY};
+
 /// Default machine type for hyperlight-nanvix
-const DEFAULT_MACHINE: &str = "hyperlight";
+pub const DEFAULT_MACHINE: &str = "hyperlight";
 
 /// Default deployment type for hyperlight-nanvix
-const DEFAULT_DEPLOYMENT: &str = "single-process";
+pub const DEFAULT_DEPLOYMENT: &str = "single-process";
+
+/// Deployment types known to the registry, used to split release directory names
+const KNOWN_DEPLOYMENTS: &[&str] = &["single-process", "multi-process", "standalone"];
+
+/// Workload types whose interpreter a release archive may provide
+const INTERPRETERS: &[WorkloadType] = &[WorkloadType::JavaScript, WorkloadType::Python];
+
+/// Marker file touched inside a release directory whenever one of its binaries is used
+const LAST_USED_MARKER: &str = ".last-used";
 
 /// Name of the nanvix-registry cache directory (matches the upstream constant).
 const CACHE_DIRECTORY_NAME: &str = "nanvix-registry";
 
-/// Return the base nanvix-registry cache directory.
+/// Return the default nanvix-registry cache directory.
 ///
 /// Uses `dirs::cache_dir()` (e.g. `~/.cache` on Linux) and falls back to the
 /// current directory when unavailable.
-fn get_cache_directory() -> PathBuf {
+pub fn default_cache_directory() -> PathBuf {
     dirs::cache_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join(CACHE_DIRECTORY_NAME)
 }
 
-/// Perform a pure filesystem probe for a cached binary.
+/// Machine and deployment type selecting which registry packages are used.
+///
+/// The registry installs each flavor into its own `<machine>-<deployment>-*`
+/// directory, e.g. `hyperlight-single-process-<version>`. nanvix is built with
+/// single-process support only: flavors of other deployment types can be
+/// selected, e.g. to manage their cached releases, but running a guest with
+/// one fails with [`crate::Error::UnsupportedFlavor`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Flavor {
+    machine: String,
+    deployment: String,
+}
+
+impl Default for Flavor {
+    fn default() -> Self {
+        Self::new(DEFAULT_MACHINE, DEFAULT_DEPLOYMENT)
+    }
+}
+
+impl Flavor {
+    /// Create a flavor from a machine type (e.g. `"hyperlight"`) and a
+    /// deployment type (e.g. `"single-process"`, `"multi-process"`, `"standalone"`)
+    pub fn new<M: Into<String>, D: Into<String>>(machine: M, deployment: D) -> Self {
+        Self {
+            machine: machine.into(),
+            deployment: deployment.into(),
+        }
+    }
+
+    /// Registry machine type
+    pub fn machine(&self) -> &str {
+        &self.machine
+    }
+
+    /// Registry deployment type
+    pub fn deployment(&self) -> &str {
+        &self.deployment
+    }
+
+    /// Split a release directory name into its flavor and release identifier.
+    ///
+    /// Only deployment types known to the registry are recognised, since
+    /// deployment names themselves contain dashes.
+    pub fn from_directory(dir_name: &str) -> Option<(Self, &str)> {
+        let (machine, rest) = dir_name.split_once('-')?;
+        KNOWN_DEPLOYMENTS.iter().find_map(|deployment| {
+            let release = rest
+                .strip_prefix(deployment)?
+                .strip_prefix('-')
+                .filter(|release| !release.is_empty())?;
+            Some((Self::new(machine, *deployment), release))
+        })
+    }
+
+    /// Release identifier of a cache directory entry installed for this flavor
+    fn release_of<'a>(&self, dir_name: &'a str) -> Option<&'a str> {
+        Self::from_directory(dir_name)
+            .filter(|(flavor, _)| flavor == self)
+            .map(|(_, release)| release)
+    }
+
+    /// Fail unless nanvix was built with support for this flavor's deployment type
+    pub(crate) fn check_supported(&self) -> crate::Result<()> {
+        if self.deployment == DEFAULT_DEPLOYMENT {
+            Ok(())
+        } else {
+            Err(crate::Error::UnsupportedFlavor(self.to_string()))
+        }
+    }
+}
+
+impl std::fmt::Display for Flavor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.machine, self.deployment)
+    }
+}
+
+impl std::str::FromStr for Flavor {
+    type Err = crate::Error;
+
+    /// Parse a `<machine>/<deployment>` pair such as `hyperlight/single-process`.
+    ///
+    /// The deployment must be one the registry knows. Only `single-process`
+    /// flavors can run guests, see [`Flavor`].
+    fn from_str(value: &str) -> crate::Result<Self> {
+        match value.split_once('/') {
+            Some((machine, deployment))
+                if !machine.is_empty()
+                    && !machine.contains('-')
+                    && KNOWN_DEPLOYMENTS.contains(&deployment) =>
+            {
+                Ok(Self::new(machine, deployment))
+            }
+            _ => Err(crate::Error::InvalidFlavor(value.to_string())),
+        }
+    }
+}
+
+/// Extract the numeric version of a release identifier.
+///
+/// Uses the right-most `-`-separated token that is a dotted list of numbers,
+/// optionally prefixed with `v`, e.g. `3.12.1` for `python-3.12.1`.
+fn release_version(release: &str) -> Option<Vec<u64>> {
+    release.rsplit('-').find_map(|token| {
+        let token = token.strip_prefix('v').unwrap_or(token);
+        token.split('.').map(|part| part.parse().ok()).collect()
+    })
+}
+
+/// Order releases by version, falling back to their name so the order never
+/// depends on directory listing order.
+fn compare_releases(a: &str, b: &str) -> std::cmp::Ordering {
+    match (release_version(a), release_version(b)) {
+        (Some(va), Some(vb)) => va.cmp(&vb).then_with(|| a.cmp(b)),
+        (Some(_), None) => std::cmp::Ordering::Greater,
+        (None, Some(_)) => std::cmp::Ordering::Less,
+        (None, None) => a.cmp(b),
+    }
+}
+
+/// Version requirement for a registry package, such as `3.12.x`, `3.12` or `*`.
+///
+/// Each dotted component is either a number that must match exactly or a
+/// wildcard (`x` or `*`). Missing trailing components match anything, so
+/// `3.12` is equivalent to `3.12.x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    raw: String,
+    components: Vec<Option<u64>>,
+}
+
+impl VersionReq {
+    /// Whether the version of a release satisfies this requirement
+    pub fn matches(&self, release: &str) -> bool {
+        let Some(version) = release_version(release) else {
+            return false;
+        };
+        self.components
+            .iter()
+            .enumerate()
+            .all(|(i, component)| match component {
+                Some(expected) => version.get(i) == Some(expected),
+                None => true,
+            })
+    }
+}
+
+impl std::fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl std::str::FromStr for VersionReq {
+    type Err = crate::Error;
+
+    fn from_str(value: &str) -> crate::Result<Self> {
+        let raw = value.trim();
+        let invalid = || crate::Error::InvalidVersion(value.to_string());
+        if raw.is_empty() {
+            return Err(invalid());
+        }
+        let components = match raw {
+            "*" | "x" | "X" => Vec::new(),
+            _ => raw
+                .split('.')
+                .map(|part| match part {
+                    "*" | "x" | "X" => Ok(None),
+                    _ => part.parse().map(Some).map_err(|_| invalid()),
+                })
+                .collect::<crate::Result<_>>()?,
+        };
+        Ok(Self {
+            raw: raw.to_string(),
+            components,
+        })
+    }
+}
+
+/// Which cached release of a binary a lookup may return
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Release {
+    /// The newest cached release
+    #[default]
+    Latest,
+    /// The newest cached release satisfying a version requirement
+    Matching(VersionReq),
+    /// Exactly this release, e.g. one recorded in a lockfile
+    Exact(String),
+}
+
+impl Release {
+    fn accepts(&self, release: &str) -> bool {
+        match self {
+            Release::Latest => true,
+            Release::Matching(requirement) => requirement.matches(release),
+            Release::Exact(expected) => expected == release,
+        }
+    }
+}
+
+impl std::fmt::Display for Release {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Release::Latest => f.write_str("latest"),
+            Release::Matching(requirement) => write!(f, "{}", requirement),
+            Release::Exact(release) => write!(f, "={}", release),
+        }
+    }
+}
+
+/// A binary found in the cache together with the release that provides it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedBinary {
+    /// Release identifier, the cache directory name without the flavor prefix
+    pub release: String,
+    /// Absolute path of the binary
+    pub path: PathBuf,
+}
+
+/// A release directory in the cache, as reported by [`Cache::list_releases`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseInfo {
+    /// Directory name, e.g. `hyperlight-single-process-0.4.2`
+    pub name: String,
+    /// Machine/deployment flavor, `None` if the name does not follow the registry layout
+    pub flavor: Option<Flavor>,
+    /// Release identifier, the directory name without the flavor prefix
+    pub release: String,
+    /// Absolute path of the release directory
+    pub path: PathBuf,
+    /// Names of the binaries in the release's `bin` directory, sorted
+    pub binaries: Vec<String>,
+    /// Total size of the release directory in bytes
+    pub size: u64,
+    /// When a binary of the release was last used by a run, if ever
+    pub last_used: Option<SystemTime>,
+}
+
+impl ReleaseInfo {
+    fn read(path: PathBuf) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let (flavor, release) = match Flavor::from_directory(&name) {
+            Some((flavor, release)) => (Some(flavor), release.to_string()),
+            None => (None, name.clone()),
+        };
+        let mut binaries: Vec<String> = std::fs::read_dir(path.join("bin"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_file()))
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        binaries.sort();
+        let last_used = std::fs::metadata(path.join(LAST_USED_MARKER))
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        Self {
+            size: directory_size(&path),
+            name,
+            flavor,
+            release,
+            path,
+            binaries,
+            last_used,
+        }
+    }
+}
+
+/// Total size of the regular files below a directory, without following symlinks
+fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(ft) if ft.is_dir() => directory_size(&entry.path()),
+            Ok(ft) if ft.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Release name implied by an archive file name, e.g. `hyperlight-single-process-0.4.2`
+/// for `hyperlight-single-process-0.4.2.tar.gz`
+fn archive_release_name(archive: &Path) -> Option<String> {
+    let file_name = archive.file_name()?.to_str()?;
+    file_name
+        .strip_suffix(".tar.gz")
+        .or_else(|| file_name.strip_suffix(".tgz"))
+        .map(str::to_string)
+}
+
+/// First symbolic link below `root` whose target lies outside `root`.
 ///
-/// Scans every `<machine>-<deployment>-*/bin/<binary_name>` path inside the
-/// cache directory and returns the first match. This never hits the network.
-fn find_in_local_cache(binary_name: &str) -> Option<String> {
-    let cache_dir = get_cache_directory();
-    let prefix = format!("{}-{}-", DEFAULT_MACHINE, DEFAULT_DEPLOYMENT);
-
-    let entries = std::fs::read_dir(&cache_dir).ok()?;
-    for entry in entries.flatten() {
-        let dir_name = entry.file_name();
-        let dir_name_str = dir_name.to_string_lossy();
-        if dir_name_str.starts_with(&prefix) && entry.file_type().is_ok_and(|ft| ft.is_dir()) {
-            let candidate = entry.path().join("bin").join(binary_name);
-            if candidate.is_file() {
-                return Some(candidate.to_string_lossy().into_owned());
+/// Targets are resolved lexically from the directory holding the link, so a
+/// link is accepted only if it is relative and never climbs above `root`.
+fn escaping_link(root: &Path) -> std::io::Result<Option<PathBuf>> {
+    let mut directories = vec![PathBuf::new()];
+    while let Some(relative) = directories.pop() {
+        for entry in std::fs::read_dir(root.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                directories.push(path);
+            } else if file_type.is_symlink() {
+                let target = std::fs::read_link(entry.path())?;
+                let mut depth = relative.components().count();
+                for component in target.components() {
+                    depth = match component {
+                        Component::Normal(_) => depth + 1,
+                        Component::CurDir => depth,
+                        Component::ParentDir if depth > 0 => depth - 1,
+                        _ => return Ok(Some(path)),
+                    };
+                }
             }
         }
     }
-    None
+    Ok(None)
+}
+
+/// Temporary directory inside the cache that is removed when dropped
+struct StagingDirectory(PathBuf);
+
+impl StagingDirectory {
+    fn new(root: &Path) -> std::io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let path = root.join(format!(".import-{}-{}", std::process::id(), nanos));
+        std::fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for StagingDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Compute the hex-encoded SHA-256 digest of a file
+pub fn sha256_file<P: AsRef<Path>>(path: P) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
 }
 
-/// Check if a binary exists in the local cache.
+/// A nanvix-registry cache rooted at a specific directory.
+///
+/// Both the local filesystem probes and the registry fallback operate on the
+/// same root, so a cache can point at a read-only directory baked into a
+/// container image or at a per-tenant directory. Lookups only consider
+/// binaries installed for the cache's [`Flavor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cache {
+    root: PathBuf,
+    /// Whether the root was chosen by the user rather than defaulted
+    custom_root: bool,
+    flavor: Flavor,
+    digests: DigestStore,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        let mut cache = Self::new(default_cache_directory());
+        cache.custom_root = false;
+        cache
+    }
+}
+
+impl Cache {
+    /// Create a cache rooted at the given directory
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            custom_root: true,
+            flavor: Flavor::default(),
+            digests: DigestStore::default(),
+        }
+    }
+
+    /// Restrict lookups and downloads to the given machine/deployment flavor
+    pub fn with_flavor(mut self, flavor: Flavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Record and verify binary digests in the given store
+    pub fn with_digest_store(mut self, digests: DigestStore) -> Self {
+        self.digests = digests;
+        self
+    }
+
+    /// Store holding the recorded digests of this cache's binaries
+    pub fn digest_store(&self) -> &DigestStore {
+        &self.digests
+    }
+
+    /// Root directory of the cache
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Machine/deployment flavor the cache is keyed on
+    pub fn flavor(&self) -> &Flavor {
+        &self.flavor
+    }
+
+    /// Create a registry client that installs into this cache
+    pub fn registry(&self) -> Registry {
+        Registry::new(self.registry_root())
+    }
+
+    /// Root handed to the registry, which picks its own default location
+    /// unless the user chose a cache directory
+    pub(crate) fn registry_root(&self) -> Option<String> {
+        self.custom_root
+            .then(|| self.root.to_string_lossy().into_owned())
+    }
+
+    /// List every cached release of this flavor that provides a binary, newest first.
+    ///
+    /// Scans every `<machine>-<deployment>-*/bin/<binary_name>` path of the
+    /// cache's flavor. This never hits the network.
+    pub fn releases(&self, binary_name: &str) -> Vec<CachedBinary> {
+        let Ok(entries) = std::fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+        let mut found: Vec<CachedBinary> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let dir_name = entry.file_name().to_string_lossy().into_owned();
+                let release = self.flavor.release_of(&dir_name)?.to_string();
+                if !entry.file_type().is_ok_and(|ft| ft.is_dir()) {
+                    return None;
+                }
+                let path = entry.path().join("bin").join(binary_name);
+                path.is_file().then_some(CachedBinary { release, path })
+            })
+            .collect();
+        found.sort_by(|a, b| compare_releases(&b.release, &a.release));
+        found
+    }
+
+    /// Locate the newest cached release of a binary accepted by `release`.
+    ///
+    /// Pure filesystem probe with no network side effects.
+    pub fn find_release(&self, binary_name: &str, release: &Release) -> Option<CachedBinary> {
+        self.releases(binary_name)
+            .into_iter()
+            .find(|cached| release.accepts(&cached.release))
+    }
+
+    /// Locate the newest cached release accepted by `release` that provides
+    /// every one of the binaries, returned in the same order.
+    ///
+    /// Binaries of a run must come from one release, since a release ships
+    /// the kernel and the interpreters built together.
+    pub fn find_release_providing(
+        &self,
+        binary_names: &[&str],
+        release: &Release,
+    ) -> Option<Vec<CachedBinary>> {
+        let (first, rest) = binary_names.split_first()?;
+        self.releases(first)
+            .into_iter()
+            .filter(|cached| release.accepts(&cached.release))
+            .find_map(|cached| {
+                let bin_dir = cached.path.parent()?.to_path_buf();
+                let mut binaries = vec![cached.clone()];
+                for binary_name in rest {
+                    let path = bin_dir.join(binary_name);
+                    if !path.is_file() {
+                        return None;
+                    }
+                    binaries.push(CachedBinary {
+                        release: cached.release.clone(),
+                        path,
+                    });
+                }
+                Some(binaries)
+            })
+    }
+
+    /// Locate a release of a binary, downloading it from the registry if not found locally.
+    ///
+    /// The registry always installs its current release, so this returns `None`
+    /// when that release does not satisfy `release` either.
+    pub async fn fetch_release(
+        &self,
+        binary_name: &str,
+        release: &Release,
+    ) -> Option<CachedBinary> {
+        if let Some(cached) = self.find_release(binary_name, release) {
+            return Some(cached);
+        }
+
+        self.registry()
+            .get_cached_binary(self.flavor.machine(), self.flavor.deployment(), binary_name)
+            .await
+            .ok()?;
+        let found = self.find_release(binary_name, release)?;
+        self.record_installed(std::slice::from_ref(&found)).ok()?;
+        Some(found)
+    }
+
+    /// Check a cached binary against the digest recorded when it was installed.
+    ///
+    /// Returns the hex-encoded SHA-256 digest of the binary. A binary without
+    /// a recorded digest fails with [`crate::Error::DigestMissing`].
+    pub fn verify(&self, binary: &CachedBinary) -> crate::Result<String> {
+        self.digests.verify(&binary.path)
+    }
+
+    /// Record the digests of every binary of this flavor already in the cache
+    /// that has none, and return their paths.
+    ///
+    /// This adopts a cache populated before digests were recorded, or by hand,
+    /// trusting its binaries as they are now; only do it after checking the
+    /// cache. Binaries whose recorded digest no longer matches still fail with
+    /// [`crate::Error::IntegrityMismatch`].
+    pub fn trust_existing(&self) -> crate::Result<Vec<PathBuf>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut trusted = Vec::new();
+        for entry in entries.flatten() {
+            let dir_name = entry.file_name().to_string_lossy().into_owned();
+            if self.flavor.release_of(&dir_name).is_none() {
+                continue;
+            }
+            let Ok(binaries) = std::fs::read_dir(entry.path().join("bin")) else {
+                continue;
+            };
+            for binary in binaries.flatten() {
+                let path = binary.path();
+                if !path.is_file() {
+                    continue;
+                }
+                match self.digests.verify(&path) {
+                    Err(crate::Error::DigestMissing(_)) => {
+                        self.digests.record(&path)?;
+                        trusted.push(path);
+                    }
+                    result => {
+                        result?;
+                    }
+                }
+            }
+        }
+        trusted.sort();
+        Ok(trusted)
+    }
+
+    /// Record the digests of binaries the registry has just installed.
+    ///
+    /// Digests recorded earlier are kept, so a binary that was modified since
+    /// it was installed still fails verification.
+    pub fn record_installed(&self, binaries: &[CachedBinary]) -> crate::Result<()> {
+        binaries
+            .iter()
+            .try_for_each(|binary| self.digests.record_new(&binary.path))
+    }
+
+    /// Record that a cached binary was used, for [`ReleaseInfo::last_used`].
+    ///
+    /// Best effort: a read-only cache simply reports no last use.
+    pub fn mark_used(&self, binary: &CachedBinary) {
+        if let Some(release_dir) = binary.path.parent().and_then(Path::parent) {
+            let _ = std::fs::write(release_dir.join(LAST_USED_MARKER), b"");
+        }
+    }
+
+    /// List every release directory in the cache, of any flavor, sorted by name
+    pub fn list_releases(&self) -> crate::Result<Vec<ReleaseInfo>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut releases: Vec<ReleaseInfo> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_dir()))
+            // Skip hidden directories such as in-progress imports
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .map(|entry| ReleaseInfo::read(entry.path()))
+            .collect();
+        releases.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(releases)
+    }
+
+    /// Describe a single release directory
+    pub fn release_info(&self, name: &str) -> crate::Result<ReleaseInfo> {
+        Ok(ReleaseInfo::read(self.release_directory(name)?))
+    }
+
+    /// Delete a release directory and forget the digests of its binaries
+    pub fn remove_release(&self, name: &str) -> crate::Result<ReleaseInfo> {
+        let info = self.release_info(name)?;
+        std::fs::remove_dir_all(&info.path)?;
+        self.digests.forget_under(&info.path)?;
+        Ok(info)
+    }
+
+    /// Keep the `keep` most recently used releases of every flavor and remove the rest.
+    ///
+    /// Releases that were never used are ordered by version. Directories that
+    /// do not follow the registry layout are left alone. Returns the removed releases.
+    pub fn prune(&self, keep: usize) -> crate::Result<Vec<ReleaseInfo>> {
+        let mut by_flavor: std::collections::BTreeMap<String, Vec<ReleaseInfo>> =
+            std::collections::BTreeMap::new();
+        for info in self.list_releases()? {
+            if let Some(flavor) = &info.flavor {
+                by_flavor.entry(flavor.to_string()).or_default().push(info);
+            }
+        }
+
+        let mut removed = Vec::new();
+        for mut releases in by_flavor.into_values() {
+            releases.sort_by(|a, b| {
+                b.last_used
+                    .cmp(&a.last_used)
+                    .then_with(|| compare_releases(&b.release, &a.release))
+            });
+            for info in releases.into_iter().skip(keep) {
+                removed.push(self.remove_release(&info.name)?);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Unpack a `.tar.gz` release archive into the cache.
+    ///
+    /// The archive either holds a single `<machine>-<deployment>-<version>`
+    /// directory, or the `bin/` and `lib/` directories at its root, in which
+    /// case the release name is taken from the archive file name. Links must
+    /// stay inside the release, and the release must provide the kernel and
+    /// at least one interpreter.
+    pub fn import_release<P: AsRef<Path>>(&self, archive: P) -> crate::Result<ReleaseInfo> {
+        let archive = archive.as_ref();
+        let invalid = |reason: &str| crate::Error::InvalidArchive {
+            path: archive.to_path_buf(),
+            reason: reason.to_string(),
+        };
+
+        // Unpack next to the final location so the release appears atomically
+        std::fs::create_dir_all(&self.root)?;
+        let staging = StagingDirectory::new(&self.root)?;
+        let file = std::fs::File::open(archive)?;
+        let mut tarball = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        for entry in tarball.entries()? {
+            let mut entry = entry?;
+            if !matches!(
+                entry.header().entry_type(),
+                tar::EntryType::Regular
+                    | tar::EntryType::Directory
+                    | tar::EntryType::Symlink
+                    | tar::EntryType::Link
+            ) {
+                return Err(invalid(
+                    "only regular files, directories and links are supported",
+                ));
+            }
+            if !entry.unpack_in(staging.path())? {
+                return Err(invalid("entry escapes the release directory"));
+            }
+        }
+
+        let top_level: Vec<String> = std::fs::read_dir(staging.path())?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        let (content, name) = match top_level.as_slice() {
+            [name] if Flavor::from_directory(name).is_some() => {
+                (staging.path().join(name), name.clone())
+            }
+            _ => {
+                let name = archive_release_name(archive)
+                    .filter(|name| Flavor::from_directory(name).is_some())
+                    .ok_or_else(|| {
+                        invalid("cannot tell the <machine>-<deployment>-<version> release name")
+                    })?;
+                (staging.path().to_path_buf(), name)
+            }
+        };
+
+        if escaping_link(&content)?.is_some() {
+            return Err(invalid("link escapes the release directory"));
+        }
+        let bin_dir = content.join("bin");
+        if !bin_dir.join(KERNEL_BINARY).is_file() {
+            return Err(invalid("missing bin/kernel.elf"));
+        }
+        if !INTERPRETERS
+            .iter()
+            .any(|interpreter| bin_dir.join(interpreter.binary_name()).is_file())
+        {
+            return Err(invalid("missing an interpreter, bin/qjs or bin/python3"));
+        }
+        if !content.join("lib").is_dir() {
+            return Err(invalid("missing lib/ directory"));
+        }
+
+        let destination = self.root.join(&name);
+        if destination.exists() {
+            return Err(crate::Error::ReleaseExists(name));
+        }
+        std::fs::rename(&content, &destination)?;
+        self.digests.forget_under(&destination)?;
+        for entry in std::fs::read_dir(destination.join("bin"))?.flatten() {
+            if entry.path().is_file() {
+                self.digests.record(&entry.path())?;
+            }
+        }
+        Ok(ReleaseInfo::read(destination))
+    }
+
+    /// Pack a release directory into `<destination>/<release>.tar.gz`.
+    ///
+    /// The archive holds the release directory itself, so it can be passed to
+    /// [`Cache::import_release`] unchanged. Links inside the release are kept
+    /// as links, a release with a link leading outside of it is not exported.
+    /// Returns the path of the archive.
+    pub fn export_release<P: AsRef<Path>>(
+        &self,
+        name: &str,
+        destination: P,
+    ) -> crate::Result<PathBuf> {
+        let release_dir = self.release_directory(name)?;
+        std::fs::create_dir_all(destination.as_ref())?;
+        let archive = destination.as_ref().join(format!("{}.tar.gz", name));
+        if let Some(link) = escaping_link(&release_dir)? {
+            return Err(crate::Error::InvalidArchive {
+                path: archive,
+                reason: format!("{} links outside the release", link.display()),
+            });
+        }
+
+        let file = std::fs::File::create(&archive)?;
+        let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        builder.follow_symlinks(false);
+        builder.append_dir(name, &release_dir)?;
+        for entry in std::fs::read_dir(&release_dir)?.flatten() {
+            let entry_name = entry.file_name();
+            if entry_name == LAST_USED_MARKER {
+                continue;
+            }
+            let archive_path = Path::new(name).join(&entry_name);
+            if entry.file_type()?.is_dir() {
+                builder.append_dir_all(&archive_path, entry.path())?;
+            } else {
+                builder.append_path_with_name(entry.path(), &archive_path)?;
+            }
+        }
+        builder.into_inner()?.finish()?;
+        Ok(archive)
+    }
+
+    /// Resolve a release directory name, refusing anything outside the cache
+    fn release_directory(&self, name: &str) -> crate::Result<PathBuf> {
+        let path = self.root.join(name);
+        let is_plain_name = Path::new(name).file_name() == Some(std::ffi::OsStr::new(name));
+        if !is_plain_name || !path.is_dir() {
+            return Err(crate::Error::ReleaseNotFound(name.to_string()));
+        }
+        Ok(path)
+    }
+
+    /// Perform a pure filesystem probe for the newest cached release of a binary
+    fn find_in_local_cache(&self, binary_name: &str) -> Option<String> {
+        self.find_release(binary_name, &Release::Latest)
+            .map(|cached| cached.path.to_string_lossy().into_owned())
+    }
+
+    /// Check if a binary exists in the cache.
+    ///
+    /// Pure filesystem probe with no network side effects.
+    pub fn is_binary_cached(&self, binary_name: &str) -> bool {
+        self.find_in_local_cache(binary_name).is_some()
+    }
+
+    /// Locate a binary in the cache only.
+    ///
+    /// Pure filesystem probe with no network side effects, used in offline mode.
+    pub fn find_cached_binary_path(&self, binary_name: &str) -> Option<String> {
+        self.find_in_local_cache(binary_name)
+    }
+
+    /// Locate a cached binary, downloading it from the registry if not found locally.
+    ///
+    /// First probes the local filesystem. If the binary is not present, falls back
+    /// to the nanvix registry which will download it into this cache.
+    pub async fn get_cached_binary_path(&self, binary_name: &str) -> Option<String> {
+        // Try local filesystem first.
+        if let Some(path) = self.find_in_local_cache(binary_name) {
+            return Some(path);
+        }
+
+        // Fall back to the nanvix registry (downloads if needed).
+        let path = self
+            .registry()
+            .get_cached_binary(self.flavor.machine(), self.flavor.deployment(), binary_name)
+            .await
+            .ok()?;
+        self.digests.record_new(Path::new(&path)).ok()?;
+        Some(path)
+    }
+}
+
+/// Check if a binary exists in the default cache.
 ///
 /// Pure filesystem probe with no network side effects.
 pub fn is_binary_cached(binary_name: &str) -> bool {
-    find_in_local_cache(binary_name).is_some()
+    Cache::default().is_binary_cached(binary_name)
 }
 
-/// Locate a cached binary, downloading it from the registry if not found locally.
+/// Locate a binary in the default cache only.
 ///
-/// First probes the local filesystem. If the binary is not present, falls back
-/// to the nanvix registry which will download and cache it.
+/// Pure filesystem probe with no network side effects, used in offline mode.
+pub fn find_cached_binary_path(binary_name: &str) -> Option<String> {
+    Cache::default().find_cached_binary_path(binary_name)
+}
+
+/// Locate a binary in the default cache, downloading it from the registry if not found locally.
 pub async fn get_cached_binary_path(binary_name: &str) -> Option<String> {
-    // Try local filesystem first.
-    if let Some(path) = find_in_local_cache(binary_name) {
-        return Some(path);
-    }
+    Cache::default().get_cached_binary_path(binary_name).await
+}
+
+/// List every release directory in the default cache.
+pub fn list_releases() -> crate::Result<Vec<ReleaseInfo>> {
+    Cache::default().list_releases()
+}
+
+/// Describe a single release directory in the default cache.
+pub fn release_info(name: &str) -> crate::Result<ReleaseInfo> {
+    Cache::default().release_info(name)
+}
+
+/// Delete a release directory from the default cache.
+pub fn remove_release(name: &str) -> crate::Result<ReleaseInfo> {
+    Cache::default().remove_release(name)
+}
+
+/// Keep the `keep` most recently used releases of every flavor in the default cache.
+pub fn prune(keep: usize) -> crate::Result<Vec<ReleaseInfo>> {
+    Cache::default().prune(keep)
+}
+
+/// Unpack a `.tar.gz` release archive into the default cache.
+pub fn import_release<P: AsRef<Path>>(archive: P) -> crate::Result<ReleaseInfo> {
+    Cache::default().import_release(archive)
+}
 
-    // Fall back to the nanvix registry (downloads if needed).
-    let registry = Registry::new(None);
-    registry
-        .get_cached_binary(DEFAULT_MACHINE, DEFAULT_DEPLOYMENT, binary_name)
-        .await
-        .ok()
+/// Pack a release directory of the default cache into `<destination>/<release>.tar.gz`.
+pub fn export_release<P: AsRef<Path>>(name: &str, destination: P) -> crate::Result<PathBuf> {
+    Cache::default().export_release(name, destination)
 }
@@ -0,0 +1,129 @@
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+/// First bytes of every ELF file
+pub(crate) const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const ET_REL: u16 = 1;
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+const EM_386: u16 = 3;
+const PT_LOAD: u32 = 1;
+const PT_INTERP: u32 = 3;
+const PF_X: u32 = 1;
+
+/// Size of the 32-bit ELF header and of a 32-bit program header
+const EHDR32_SIZE: usize = 52;
+const PHDR32_SIZE: usize = 32;
+
+/// Check that an ELF file can run as a Nanvix guest.
+///
+/// Nanvix runs statically linked 32-bit i686 executables, so anything else is
+/// rejected here rather than crashing the guest. Only the ELF and program
+/// headers are checked: the entry point must lie in an executable segment.
+/// The load addresses are not compared with the `user.ld` linker script of
+/// the release, so a binary linked for another layout is only caught by the
+/// guest.
+pub(crate) fn check_guest_binary(path: &Path) -> Result<()> {
+    let invalid = |reason: String| {
+        Error::InvalidWorkload(format!(
+            "{:?} {}, Nanvix runs statically linked i686 executables built with the Nanvix toolchain",
+            path, reason
+        ))
+    };
+    let file = File::open(path)?;
+    let mut header = [0; EHDR32_SIZE];
+    let read = file.read_at(&mut header, 0)?;
+    if read < ELF_MAGIC.len() || &header[..ELF_MAGIC.len()] != ELF_MAGIC {
+        return Err(invalid("is not an ELF binary".to_string()));
+    }
+    if read < 20 {
+        return Err(invalid("has a truncated ELF header".to_string()));
+    }
+
+    // The machine is at the same offset in 32 and 64-bit headers
+    let machine = u16::from_le_bytes([header[18], header[19]]);
+    if header[4] != ELFCLASS32 {
+        return Err(invalid(format!(
+            "is a 64-bit {} binary",
+            machine_name(machine)
+        )));
+    }
+    if header[5] != ELFDATA2LSB {
+        return Err(invalid("is a big-endian binary".to_string()));
+    }
+    if machine != EM_386 {
+        return Err(invalid(format!("is a {} binary", machine_name(machine))));
+    }
+    if read < EHDR32_SIZE {
+        return Err(invalid("has a truncated ELF header".to_string()));
+    }
+
+    let u16_at = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+    let u32_at = |bytes: &[u8], offset: usize| {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    };
+    match u16_at(16) {
+        ET_EXEC => {}
+        ET_REL => {
+            return Err(invalid(
+                "is an object file, link it into an executable".to_string(),
+            ))
+        }
+        ET_DYN => return Err(invalid("is position-independent".to_string())),
+        other => return Err(invalid(format!("has unsupported ELF type {}", other))),
+    }
+
+    let entry = u32_at(&header, 24);
+    let phoff = u32_at(&header, 28) as u64;
+    let phentsize = u16_at(42) as usize;
+    let phnum = u16_at(44) as usize;
+    if phentsize < PHDR32_SIZE {
+        return Err(invalid("has malformed program headers".to_string()));
+    }
+    // The header sizes come from the file, so check them against its length
+    // before allocating the program header table
+    let table_size = (phentsize * phnum) as u64;
+    if phoff + table_size > file.metadata()?.len() {
+        return Err(invalid("has truncated program headers".to_string()));
+    }
+    let mut program_headers = vec![0; table_size as usize];
+    file.read_exact_at(&mut program_headers, phoff)
+        .map_err(|_| invalid("has truncated program headers".to_string()))?;
+
+    let mut entry_mapped = false;
+    for program_header in program_headers.chunks_exact(phentsize) {
+        match u32_at(program_header, 0) {
+            PT_INTERP => return Err(invalid("is dynamically linked".to_string())),
+            PT_LOAD if u32_at(program_header, 24) & PF_X != 0 => {
+                let start = u32_at(program_header, 8) as u64;
+                let end = start + u32_at(program_header, 20) as u64;
+                entry_mapped |= (start..end).contains(&(entry as u64));
+            }
+            _ => {}
+        }
+    }
+    if !entry_mapped {
+        return Err(invalid(format!(
+            "has its entry point {:#x} outside its executable segments",
+            entry
+        )));
+    }
+    Ok(())
+}
+
+fn machine_name(machine: u16) -> String {
+    match machine {
+        3 => "i686".to_string(),
+        40 => "ARM".to_string(),
+        62 => "x86-64".to_string(),
+        183 => "AArch64".to_string(),
+        243 => "RISC-V".to_string(),
+        other => format!("machine {}", other),
+    }
+}
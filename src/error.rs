@@ -0,0 +1,191 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::limits::Limit;
+
+/// Result type used throughout the hyperlight-nanvix public API
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the hyperlight-nanvix public API
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The workload type could not be determined for the given path
+    #[error("Could not determine workload type for {0:?}")]
+    UnknownWorkloadType(PathBuf),
+
+    /// The workload file does not exist
+    #[error("Workload file not found: {0:?}")]
+    WorkloadNotFound(PathBuf),
+
+    /// The workload cannot be run as requested
+    #[error("Invalid workload: {0}")]
+    InvalidWorkload(String),
+
+    /// A machine/deployment flavor could not be parsed
+    #[error("Invalid flavor {0:?}, expected <machine>/<deployment>")]
+    InvalidFlavor(String),
+
+    /// A flavor whose deployment type this build cannot run
+    #[error("Flavor {0} is not supported, only single-process deployments can run")]
+    UnsupportedFlavor(String),
+
+    /// A package version requirement could not be parsed
+    #[error("Invalid version requirement {0:?}, expected e.g. 3.12.x")]
+    InvalidVersion(String),
+
+    /// No cached or downloadable release satisfies a version pin or lockfile entry
+    #[error("No release of {binary} matches {requirement}")]
+    VersionUnavailable { binary: String, requirement: String },
+
+    /// A lockfile could not be read or written
+    #[error("Invalid lockfile {path:?}: {reason}")]
+    InvalidLockfile { path: PathBuf, reason: String },
+
+    /// A cached binary does not match the hash recorded in the lockfile
+    #[error("{binary} from release {release} does not match the hash recorded in the lockfile")]
+    LockfileMismatch { binary: String, release: String },
+
+    /// A cached binary changed since its digest was recorded
+    #[error(
+        "{path:?} does not match its recorded SHA-256 digest (expected {expected}, found {actual}), \
+         it may have been tampered with or truncated; run clear-registry and setup-registry to re-fetch it"
+    )]
+    IntegrityMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    /// A cached binary or snapshot has no digest recorded when it was installed
+    #[error(
+        "{0:?} has no recorded SHA-256 digest, it was not installed by hyperlight-nanvix; \
+         run setup-registry --trust-existing to trust the binaries already in the cache, \
+         or clear-registry and setup-registry to install them again"
+    )]
+    DigestMissing(PathBuf),
+
+    /// A syscall policy could not be parsed or uses unsupported syscalls
+    #[error("Invalid syscall policy: {0}")]
+    InvalidPolicy(String),
+
+    /// The embedder's syscall table uses an action the runtime cannot chain to
+    #[error(
+        "The syscall table's {0} action cannot be combined with the runtime's syscall handling, \
+         use SyscallAction::Forward"
+    )]
+    UnsupportedSyscallAction(String),
+
+    /// A fault injection spec is malformed or targets a syscall that cannot be intercepted
+    #[error("Invalid fault injection: {0}")]
+    InvalidFault(String),
+
+    /// A syscall trace file is malformed
+    #[error("Invalid syscall trace: {0}")]
+    InvalidTrace(String),
+
+    /// No release directory with the given name exists in the cache
+    #[error("Release {0:?} not found in the cache")]
+    ReleaseNotFound(String),
+
+    /// A release with the same name is already in the cache
+    #[error("Release {0:?} is already in the cache, remove it first")]
+    ReleaseExists(String),
+
+    /// A release archive is malformed or does not follow the registry layout
+    #[error("Invalid release archive {path:?}: {reason}")]
+    InvalidArchive { path: PathBuf, reason: String },
+
+    /// A guest snapshot could not be taken
+    #[error("Failed to take snapshot: {0}")]
+    SnapshotFailed(String),
+
+    /// The nanvix registry could not be reached or failed to install a package
+    #[error("Nanvix registry unavailable: {0:#}")]
+    RegistryUnavailable(anyhow::Error),
+
+    /// The interpreter binary for the workload is missing from the cache and registry
+    #[error("Failed to locate {0} binary in cache or registry")]
+    InterpreterMissing(String),
+
+    /// The Nanvix kernel is missing from the cache and registry
+    #[error("Failed to locate kernel.elf in cache or registry")]
+    KernelMissing,
+
+    /// A binary is not cached and offline mode forbids downloading it
+    #[error("{0} is not cached and offline mode is enabled, run setup-registry while online")]
+    NotCached(String),
+
+    /// The guest crashed or the terminal failed while running it
+    #[error("Guest crashed: {0:#}")]
+    GuestCrashed(anyhow::Error),
+
+    /// The guest exited with a non-zero status
+    #[error("Guest exited with status {0}")]
+    GuestExit(i32),
+
+    /// The run exceeded the timeout configured with `RuntimeConfig::with_timeout`
+    #[error("Workload timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// The run exceeded one of the resource limits configured on `RuntimeConfig`
+    #[error("Workload exceeded its {0}")]
+    LimitExceeded(Limit),
+
+    /// A replayed run made different syscalls than the trace recorded
+    #[error("Replay diverged from the recorded trace: {0}")]
+    ReplayDiverged(String),
+
+    /// The run was cancelled through a `CancelHandle`
+    #[error("Workload was cancelled")]
+    Cancelled,
+
+    /// A host I/O operation failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Any other failure
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl Error {
+    /// Stable, machine-readable code identifying the kind of error
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Error::UnknownWorkloadType(_) => "UNKNOWN_WORKLOAD_TYPE",
+            Error::WorkloadNotFound(_) => "WORKLOAD_NOT_FOUND",
+            Error::InvalidWorkload(_) => "INVALID_WORKLOAD",
+            Error::InvalidFlavor(_) => "INVALID_FLAVOR",
+            Error::UnsupportedFlavor(_) => "UNSUPPORTED_FLAVOR",
+            Error::InvalidVersion(_) => "INVALID_VERSION",
+            Error::VersionUnavailable { .. } => "VERSION_UNAVAILABLE",
+            Error::InvalidLockfile { .. } => "INVALID_LOCKFILE",
+            Error::LockfileMismatch { .. } => "LOCKFILE_MISMATCH",
+            Error::IntegrityMismatch { .. } => "INTEGRITY_MISMATCH",
+            Error::DigestMissing(_) => "DIGEST_MISSING",
+            Error::InvalidPolicy(_) => "INVALID_POLICY",
+            Error::UnsupportedSyscallAction(_) => "UNSUPPORTED_SYSCALL_ACTION",
+            Error::InvalidFault(_) => "INVALID_FAULT",
+            Error::InvalidTrace(_) => "INVALID_TRACE",
+            Error::ReleaseNotFound(_) => "RELEASE_NOT_FOUND",
+            Error::ReleaseExists(_) => "RELEASE_EXISTS",
+            Error::InvalidArchive { .. } => "INVALID_ARCHIVE",
+            Error::SnapshotFailed(_) => "SNAPSHOT_FAILED",
+            Error::RegistryUnavailable(_) => "REGISTRY_UNAVAILABLE",
+            Error::InterpreterMissing(_) => "INTERPRETER_MISSING",
+            Error::KernelMissing => "KERNEL_MISSING",
+            Error::NotCached(_) => "NOT_CACHED",
+            Error::GuestCrashed(_) => "GUEST_CRASHED",
+            Error::GuestExit(_) => "GUEST_EXIT",
+            Error::Timeout(_) => "TIMEOUT",
+            Error::LimitExceeded(Limit::ProcessMemory(_)) => "PROCESS_MEMORY_LIMIT_EXCEEDED",
+            Error::LimitExceeded(Limit::ProcessCpuTime(_)) => "PROCESS_CPU_TIME_LIMIT_EXCEEDED",
+            Error::LimitExceeded(Limit::Output(_)) => "OUTPUT_LIMIT_EXCEEDED",
+            Error::ReplayDiverged(_) => "REPLAY_DIVERGED",
+            Error::Cancelled => "CANCELLED",
+            Error::Io(_) => "IO",
+            Error::Other(_) => "OTHER",
+        }
+    }
+}
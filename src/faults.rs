@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::policy::{errno_from_name, errno_name, INTERCEPTABLE_SYSCALLS};
+
+/// When an injected fault fires
+#[derive(Debug, Clone, PartialEq)]
+pub enum FaultTrigger {
+    /// On every call
+    Always,
+    /// On each call with the given probability, between 0 and 1
+    Probability(f64),
+    /// On every Nth call of the syscall, counting from the start of the run
+    EveryNth(u64),
+    /// On calls whose path matches a glob pattern, where `*` matches any
+    /// sequence of characters and `?` a single character
+    PathPattern(String),
+}
+
+/// A syscall made to fail with an error number
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fault {
+    /// Syscall to fail, e.g. `"openat"`
+    pub syscall: String,
+    /// Error number the syscall fails with
+    pub errno: i32,
+    pub trigger: FaultTrigger,
+}
+
+impl Fault {
+    pub fn new<S: Into<String>>(syscall: S, errno: i32, trigger: FaultTrigger) -> Self {
+        Self {
+            syscall: syscall.into(),
+            errno,
+            trigger,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !INTERCEPTABLE_SYSCALLS.contains(&self.syscall.as_str()) {
+            return Err(Error::InvalidFault(format!(
+                "syscall {:?} cannot be intercepted, the nanvix syscall table only exposes: {}",
+                self.syscall,
+                INTERCEPTABLE_SYSCALLS.join(", ")
+            )));
+        }
+        match self.trigger {
+            FaultTrigger::Probability(probability) if !(0.0..=1.0).contains(&probability) => {
+                Err(Error::InvalidFault(format!(
+                    "probability {} is not between 0 and 1",
+                    probability
+                )))
+            }
+            FaultTrigger::EveryNth(0) => Err(Error::InvalidFault(
+                "every=N needs N of at least 1".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::str::FromStr for Fault {
+    type Err = Error;
+
+    /// Parse `SYSCALL:ERRNO[:TRIGGER]`, where the trigger is a probability such
+    /// as `0.1`, `every=N` or `path=PATTERN`, e.g. `openat:ENOENT:0.1`
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut parts = spec.splitn(3, ':');
+        let (Some(syscall), Some(errno)) = (parts.next(), parts.next()) else {
+            return Err(Error::InvalidFault(format!(
+                "{:?}, expected SYSCALL:ERRNO[:TRIGGER]",
+                spec
+            )));
+        };
+        let errno = errno_from_name(errno)
+            .ok_or_else(|| Error::InvalidFault(format!("unknown error name {:?}", errno)))?;
+        let trigger = match parts.next() {
+            None => FaultTrigger::Always,
+            Some(trigger) => {
+                if let Some(every) = trigger.strip_prefix("every=") {
+                    FaultTrigger::EveryNth(every.parse().map_err(|_| {
+                        Error::InvalidFault(format!("invalid call count {:?}", every))
+                    })?)
+                } else if let Some(pattern) = trigger.strip_prefix("path=") {
+                    FaultTrigger::PathPattern(pattern.to_string())
+                } else {
+                    FaultTrigger::Probability(trigger.parse().map_err(|_| {
+                        Error::InvalidFault(format!(
+                            "invalid trigger {:?}, expected a probability, every=N or path=PATTERN",
+                            trigger
+                        ))
+                    })?)
+                }
+            }
+        };
+        let fault = Fault::new(syscall, errno, trigger);
+        fault.validate()?;
+        Ok(fault)
+    }
+}
+
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.syscall)?;
+        match errno_name(self.errno) {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "{}", self.errno)?,
+        }
+        match &self.trigger {
+            FaultTrigger::Always => Ok(()),
+            FaultTrigger::Probability(probability) => write!(f, ":{}", probability),
+            FaultTrigger::EveryNth(every) => write!(f, ":every={}", every),
+            FaultTrigger::PathPattern(pattern) => write!(f, ":path={}", pattern),
+        }
+    }
+}
+
+/// Decides which syscalls of a run fail.
+///
+/// Every run starts from the same seed and call counts, so a run with the
+/// same workload and syscalls sees the same faults.
+#[derive(Debug, Clone)]
+pub(crate) struct FaultInjector {
+    faults: Vec<Fault>,
+    calls: Vec<u64>,
+    rng: SplitMix64,
+}
+
+impl FaultInjector {
+    pub(crate) fn new(faults: &[Fault], seed: u64) -> Result<Self> {
+        faults.iter().try_for_each(Fault::validate)?;
+        Ok(Self {
+            faults: faults.to_vec(),
+            calls: vec![0; faults.len()],
+            rng: SplitMix64(seed),
+        })
+    }
+
+    /// Error number to fail a syscall with, if a fault fires.
+    ///
+    /// The first matching fault that fires wins, but every fault of the
+    /// syscall sees the call, so call counts do not depend on other faults.
+    pub(crate) fn inject(&mut self, syscall: &str, path: Option<&Path>) -> Option<i32> {
+        let mut errno = None;
+        for (fault, calls) in self.faults.iter().zip(self.calls.iter_mut()) {
+            if fault.syscall != syscall {
+                continue;
+            }
+            *calls += 1;
+            let fires = match &fault.trigger {
+                FaultTrigger::Always => true,
+                FaultTrigger::Probability(probability) => self.rng.next_f64() < *probability,
+                FaultTrigger::EveryNth(every) => *calls % every == 0,
+                FaultTrigger::PathPattern(pattern) => path.is_some_and(|path| {
+                    glob_matches(pattern.as_bytes(), path.as_os_str().as_encoded_bytes())
+                }),
+            };
+            if fires && errno.is_none() {
+                errno = Some(fault.errno);
+            }
+        }
+        errno
+    }
+}
+
+/// Small seeded generator, so injected faults do not depend on a random number crate
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Match `*` (any sequence) and `?` (any single byte) wildcards
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
@@ -0,0 +1,151 @@
+use std::ffi::CStr;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use nanvix::log;
+
+/// Outcome of a syscall: the returned value, or the error number it failed with
+pub type SyscallResult = std::result::Result<i32, i32>;
+
+/// Performs an `openat` with the next layer: the embedder's raw syscall table or the host
+pub type OpenatNext<'a> = &'a dyn Fn(i32, &CStr, i32, u32) -> SyscallResult;
+
+/// What an `openat` hook sees of the guest and the sandbox
+pub struct SyscallContext<'a, S> {
+    state: &'a S,
+    dirfd: i32,
+    next: OpenatNext<'a>,
+}
+
+impl<S> SyscallContext<'_, S> {
+    /// State shared by every hook of the sandbox
+    pub fn state(&self) -> &S {
+        self.state
+    }
+
+    /// Directory descriptor a relative path is resolved against, `AT_FDCWD` for the working directory
+    pub fn dirfd(&self) -> i32 {
+        self.dirfd
+    }
+
+    /// Perform the `openat` on the host, possibly with a different path, flags or mode
+    pub fn openat(&self, pathname: &CStr, flags: i32, mode: u32) -> SyscallResult {
+        (self.next)(self.dirfd, pathname, flags, mode)
+    }
+}
+
+type OpenatHook<S> = dyn Fn(&SyscallContext<'_, S>, &CStr, i32, u32) -> SyscallResult + Send + Sync;
+
+/// Safe syscall handlers with typed, per-sandbox state.
+///
+/// Hooks are plain closures; the runtime generates the `unsafe` trampolines
+/// that nanvix calls. A hook either performs the syscall through its
+/// [`SyscallContext`] or fails it with an error number:
+///
+/// ```no_run
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use hyperlight_nanvix::{RuntimeConfig, SyscallHooks};
+///
+/// let hooks = SyscallHooks::with_state(AtomicUsize::new(0)).on_openat(|ctx, path, flags, mode| {
+///     ctx.state().fetch_add(1, Ordering::Relaxed);
+///     if path.to_bytes().starts_with(b"/etc") {
+///         return Err(libc::EACCES);
+///     }
+///     ctx.openat(path, flags, mode)
+/// });
+/// let config = RuntimeConfig::new().with_syscall_hooks(hooks);
+/// ```
+///
+/// Hooks run after policies, so they only see the calls the policy allows. A hook that panics fails the syscall with `EIO`.
+pub struct SyscallHooks<S = ()> {
+    state: Arc<S>,
+    openat: Option<Box<OpenatHook<S>>>,
+}
+
+impl SyscallHooks<()> {
+    /// Create hooks without state
+    pub fn new() -> Self {
+        Self::with_state(())
+    }
+}
+
+impl Default for SyscallHooks<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Send + Sync + 'static> SyscallHooks<S> {
+    /// Create hooks sharing `state`, reachable from every hook through [`SyscallContext::state`]
+    pub fn with_state(state: S) -> Self {
+        Self::with_shared_state(Arc::new(state))
+    }
+
+    /// Create hooks sharing state the embedder keeps a handle to, e.g. to read counters after a run
+    pub fn with_shared_state(state: Arc<S>) -> Self {
+        Self {
+            state,
+            openat: None,
+        }
+    }
+
+    /// Handle `openat` calls of the guest
+    pub fn on_openat<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&SyscallContext<'_, S>, &CStr, i32, u32) -> SyscallResult + Send + Sync + 'static,
+    {
+        self.openat = Some(Box::new(hook));
+        self
+    }
+
+    /// State shared by the hooks
+    pub fn state(&self) -> &Arc<S> {
+        &self.state
+    }
+}
+
+/// Syscall handlers with their state type erased, as stored in [`crate::RuntimeConfig`].
+///
+/// Implemented by [`SyscallHooks`], which is the way to build handlers.
+pub trait SyscallHandler: Send + Sync {
+    /// Whether the handler intercepts a syscall
+    fn handles(&self, syscall: &str) -> bool;
+
+    /// Handle an `openat` call, performing it with `next` if it should go ahead
+    fn openat(
+        &self,
+        dirfd: i32,
+        pathname: &CStr,
+        flags: i32,
+        mode: u32,
+        next: OpenatNext<'_>,
+    ) -> SyscallResult;
+}
+
+impl<S: Send + Sync + 'static> SyscallHandler for SyscallHooks<S> {
+    fn handles(&self, syscall: &str) -> bool {
+        syscall == "openat" && self.openat.is_some()
+    }
+
+    fn openat(
+        &self,
+        dirfd: i32,
+        pathname: &CStr,
+        flags: i32,
+        mode: u32,
+        next: OpenatNext<'_>,
+    ) -> SyscallResult {
+        let Some(hook) = &self.openat else {
+            return next(dirfd, pathname, flags, mode);
+        };
+        let ctx = SyscallContext {
+            state: &*self.state,
+            dirfd,
+            next,
+        };
+        catch_unwind(AssertUnwindSafe(|| hook(&ctx, pathname, flags, mode))).unwrap_or_else(|_| {
+            log::warn!("openat hook panicked on {:?}", pathname);
+            Err(libc::EIO)
+        })
+    }
+}
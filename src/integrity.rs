@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::sha256_file;
+use crate::error::{Error, Result};
+
+/// File name of the default digest store
+const DIGEST_FILE_NAME: &str = "cache-digests.toml";
+
+/// Serializes read-modify-write cycles of digest stores within this process
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Return the default location of the digest store.
+///
+/// The store lives outside the cache directory, in the per-user data
+/// directory (e.g. `~/.local/share/hyperlight-nanvix` on Linux), so other
+/// users that can write to a shared cache cannot rewrite the digests too.
+pub fn default_digest_file() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hyperlight-nanvix")
+        .join(DIGEST_FILE_NAME)
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct DigestStoreContents {
+    #[serde(default)]
+    digests: BTreeMap<String, String>,
+}
+
+/// SHA-256 digests of cached binaries, keyed by their absolute path.
+///
+/// Digests are recorded when the registry installs a binary or a release is
+/// imported, and every use is checked against the recorded digest. Files
+/// without a recorded digest are never trusted, unless their digests are
+/// recorded explicitly with [`crate::cache::Cache::trust_existing`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestStore {
+    path: PathBuf,
+}
+
+impl Default for DigestStore {
+    fn default() -> Self {
+        Self::new(default_digest_file())
+    }
+}
+
+impl DigestStore {
+    /// Use the digest store at the given file
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Path of the digest store file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Verify a binary against its recorded digest.
+    ///
+    /// Returns the digest of the binary, [`Error::IntegrityMismatch`] if the
+    /// file changed since its digest was recorded, or [`Error::DigestMissing`]
+    /// if no digest was ever recorded for it.
+    pub fn verify(&self, binary: &Path) -> Result<String> {
+        let actual = sha256_file(binary)?;
+        let key = binary.to_string_lossy().into_owned();
+
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        match self.load()?.digests.get(&key) {
+            Some(expected) if *expected != actual => Err(Error::IntegrityMismatch {
+                path: binary.to_path_buf(),
+                expected: expected.clone(),
+                actual,
+            }),
+            Some(_) => Ok(actual),
+            None => Err(Error::DigestMissing(binary.to_path_buf())),
+        }
+    }
+
+    /// Record the current digest of a binary, replacing any recorded digest.
+    ///
+    /// Only call this right after the binary was installed or written by
+    /// hyperlight-nanvix itself.
+    pub fn record(&self, binary: &Path) -> Result<String> {
+        let actual = sha256_file(binary)?;
+        let key = binary.to_string_lossy().into_owned();
+
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut contents = self.load()?;
+        contents.digests.insert(key, actual.clone());
+        self.save(&contents)?;
+        Ok(actual)
+    }
+
+    /// Record the digest of a binary unless one is recorded already, so a
+    /// binary that changed since keeps failing verification
+    pub fn record_new(&self, binary: &Path) -> Result<()> {
+        let key = binary.to_string_lossy().into_owned();
+        let recorded = {
+            let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            self.load()?.digests.contains_key(&key)
+        };
+        if !recorded {
+            self.record(binary)?;
+        }
+        Ok(())
+    }
+
+    /// Forget the digests of every binary below `root`, e.g. after clearing a cache
+    pub fn forget_under(&self, root: &Path) -> Result<()> {
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut contents = self.load()?;
+        let before = contents.digests.len();
+        contents
+            .digests
+            .retain(|path, _| !Path::new(path).starts_with(root));
+        if contents.digests.len() != before {
+            self.save(&contents)?;
+        }
+        Ok(())
+    }
+
+    fn load(&self) -> Result<DigestStoreContents> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| {
+                Error::Other(anyhow::anyhow!(
+                    "Invalid digest store {:?}: {}",
+                    self.path,
+                    e
+                ))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(DigestStoreContents::default())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, contents: &DigestStoreContents) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string(contents).map_err(|e| Error::Other(e.into()))?;
+
+        // Write to a temporary file first so a concurrent reader never sees a
+        // partially written store.
+        let tmp_path = self
+            .path
+            .with_extension(format!("tmp-{}", std::process::id()));
+        std::fs::write(&tmp_path, text)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
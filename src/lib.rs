@@ -1,8 +1,21 @@
-use anyhow::Result;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
+pub mod audit;
 pub mod cache;
+mod elf;
+pub mod error;
+pub mod faults;
+pub mod hooks;
+pub mod integrity;
+pub mod limits;
+pub mod lockfile;
+pub mod policy;
+pub mod pool;
 pub mod runtime;
+pub mod snapshot;
+mod syscalls;
+pub mod trace;
 
 #[cfg(feature = "napi")]
 pub mod napi;
@@ -13,27 +26,120 @@ pub mod python;
 #[cfg(test)]
 mod unit_tests;
 
-use runtime::Runtime;
-pub use runtime::{RuntimeConfig, WorkloadType};
+pub use audit::{AuditSink, JsonLinesSink, SyscallEvent};
+pub use cache::Flavor;
+pub use error::{Error, Result};
+pub use faults::{Fault, FaultTrigger};
+pub use hooks::{SyscallContext, SyscallHandler, SyscallHooks, SyscallResult};
+pub use limits::{Limit, ResourceLimits};
+pub use policy::{PolicyAction, PolicyRule, SyscallPolicy};
+pub use pool::{PoolConfig, SandboxPool};
+pub use runtime::{CancelHandle, ExitStatus, RunOutput, RuntimeConfig, WorkloadType};
+use runtime::{Guest, Runtime};
+pub use trace::SyscallTrace;
 
 // Re-export Nanvix sandbox types for syscall table configuration
 pub use nanvix::sandbox::{SyscallAction, SyscallTable};
 
-/// Main entry point for creating and running Nanvix sandboxes
+/// Main entry point for creating and running Nanvix sandboxes.
+///
+/// The sandbox keeps one guest terminal per JavaScript and Python workload
+/// type alive between runs, so a second run of the same workload type reuses
+/// the booted kernel, its sockets and toolchain state. Binary workloads get a
+/// fresh guest for every run, as the guest is started for one binary. A guest
+/// whose run failed is torn down and a fresh one is booted by the next run.
+/// Call [`Sandbox::shutdown`], or drop the sandbox, to tear the guests down.
 pub struct Sandbox {
     runtime: Runtime,
+    guests: HashMap<WorkloadType, Guest>,
 }
 
 impl Sandbox {
     /// Create a new Sandbox instance
     pub fn new(config: RuntimeConfig) -> Result<Self> {
         let runtime = Runtime::new(config)?;
-        Ok(Self { runtime })
+        Ok(Self {
+            runtime,
+            guests: HashMap::new(),
+        })
     }
 
-    /// Run a workload in the sandbox
-    pub async fn run<P: AsRef<Path>>(&mut self, workload_path: P) -> Result<()> {
-        self.runtime.run(workload_path).await
+    /// Run a workload in the sandbox and return its captured output
+    pub async fn run<P: AsRef<Path>>(&mut self, workload_path: P) -> Result<RunOutput> {
+        self.run_with_args(workload_path, &[] as &[&str]).await
+    }
+
+    /// Run a workload in the sandbox, passing additional command-line arguments to it
+    pub async fn run_with_args<P: AsRef<Path>, S: AsRef<str>>(
+        &mut self,
+        workload_path: P,
+        args: &[S],
+    ) -> Result<RunOutput> {
+        let workload_path = workload_path.as_ref();
+        let workload_type = Runtime::resolve_workload(workload_path)?;
+
+        // The guest is taken out while it runs, so dropping the run future tears it down
+        let mut guest = match self.guests.remove(&workload_type) {
+            Some(guest) => guest,
+            None => self.runtime.prepare_guest(workload_type).await?,
+        };
+        let result = self.runtime.execute(&mut guest, workload_path, args).await;
+        if result.is_ok() && workload_type != WorkloadType::Binary {
+            self.guests.insert(workload_type, guest);
+        }
+        result
+    }
+
+    /// Run inline JavaScript or Python source code without writing it to a file first
+    pub async fn run_source(
+        &mut self,
+        workload_type: WorkloadType,
+        source: &str,
+    ) -> Result<RunOutput> {
+        let source_file = self.runtime.stage_source(workload_type, source)?;
+        self.run(&source_file.0).await
+    }
+
+    /// Tear down the guests kept alive between runs.
+    ///
+    /// The sandbox stays usable, the next run boots a fresh guest.
+    pub fn shutdown(&mut self) {
+        self.guests.clear();
+    }
+
+    /// Number of guests prepared by this sandbox, a run on a reused guest does
+    /// not prepare a new one
+    #[doc(hidden)]
+    pub fn guests_prepared(&self) -> usize {
+        self.runtime.guests_prepared()
+    }
+
+    /// Number of guests this sandbox restored from a persisted snapshot
+    #[doc(hidden)]
+    pub fn snapshots_restored(&self) -> usize {
+        self.runtime.snapshots_restored()
+    }
+
+    /// Get a handle that can cancel the workload running in this sandbox.
+    ///
+    /// Dropping the future returned by [`Sandbox::run`] also tears the guest down.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.runtime.cancel_handle()
+    }
+
+    /// Take a snapshot of a booted guest with an initialised interpreter.
+    ///
+    /// Later runs of the same workload type restore the snapshot instead of
+    /// booting the kernel and starting the interpreter. Snapshots are keyed on
+    /// the kernel and interpreter digests, so they are never restored after
+    /// either binary changed. Returns the path of the persisted snapshot.
+    pub async fn snapshot(&self, workload_type: WorkloadType) -> Result<PathBuf> {
+        self.runtime.snapshot(workload_type).await
+    }
+
+    /// Remove every persisted snapshot, returning how many were removed
+    pub fn clear_snapshots(&self) -> Result<usize> {
+        self.runtime.clear_snapshots()
     }
 
     /// Clear the binary cache to force fresh downloads on next run
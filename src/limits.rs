@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Interval at which a running guest is checked against its resource limits
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A resource limit exceeded by a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    /// Memory the host process allocated during the run, in bytes
+    ProcessMemory(u64),
+    /// CPU time the host process consumed during the run
+    ProcessCpuTime(Duration),
+    /// Console output produced by the run, in bytes
+    Output(u64),
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::ProcessMemory(bytes) => {
+                write!(f, "host process memory limit of {} bytes", bytes)
+            }
+            Limit::ProcessCpuTime(budget) => {
+                write!(f, "host process CPU-time limit of {:?}", budget)
+            }
+            Limit::Output(bytes) => write!(f, "output limit of {} bytes", bytes),
+        }
+    }
+}
+
+/// Resource limits applied to every run of a runtime.
+///
+/// The limits are enforced by the host while the guest runs, and a run that
+/// exceeds one is torn down with [`crate::Error::LimitExceeded`]. The pinned
+/// nanvix revision does not let the host size guest memory or find the vCPU
+/// thread of a guest, so the memory and CPU-time limits are watchdogs on the
+/// whole host process rather than per-guest quotas: they charge a run with
+/// everything the process does while it executes. Runs of a runtime with
+/// either limit execute one at a time, but work elsewhere in the process,
+/// including runs of other runtimes, still counts against their budgets.
+///
+/// Per-sandbox memory and CPU limits are not available: the guest's memory
+/// and vCPUs keep the sizes nanvix picks by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Maximum memory the host process may allocate during a run, in bytes
+    pub process_memory: Option<u64>,
+    /// Maximum CPU time the host process may consume during a run
+    pub process_cpu_time: Option<Duration>,
+    /// Maximum console output a run may produce, in bytes
+    pub output: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Returns `true` if no limit is configured
+    pub fn is_unlimited(&self) -> bool {
+        self.process_memory.is_none() && self.process_cpu_time.is_none() && self.output.is_none()
+    }
+
+    /// Returns `true` if a limit is measured for the whole host process
+    pub(crate) fn measures_process(&self) -> bool {
+        self.process_memory.is_some() || self.process_cpu_time.is_some()
+    }
+}
+
+/// Watches a running guest and reports the first limit it exceeds
+pub(crate) struct ResourceMonitor {
+    limits: ResourceLimits,
+    console_path: PathBuf,
+    console_offset: u64,
+    cpu_time_start: Duration,
+    memory_start: u64,
+}
+
+impl ResourceMonitor {
+    /// Start measuring a run whose output is appended to `console_path` after `console_offset`
+    pub(crate) fn start(limits: ResourceLimits, console_path: &Path, console_offset: u64) -> Self {
+        Self {
+            limits,
+            console_path: console_path.to_path_buf(),
+            console_offset,
+            cpu_time_start: process_cpu_time(),
+            memory_start: resident_memory(),
+        }
+    }
+
+    /// Resolve with the first exceeded limit, or never if no limit is configured
+    pub(crate) async fn exceeded(&self) -> Limit {
+        if self.limits.is_unlimited() {
+            return std::future::pending().await;
+        }
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if let Some(limit) = self.check() {
+                return limit;
+            }
+        }
+    }
+
+    /// Return the first exceeded limit, if any
+    pub(crate) fn check(&self) -> Option<Limit> {
+        if let Some(output) = self.limits.output {
+            let written = std::fs::metadata(&self.console_path)
+                .map(|metadata| metadata.len().saturating_sub(self.console_offset))
+                .unwrap_or(0);
+            if written > output {
+                return Some(Limit::Output(output));
+            }
+        }
+        if let Some(cpu_time) = self.limits.process_cpu_time {
+            if process_cpu_time().saturating_sub(self.cpu_time_start) > cpu_time {
+                return Some(Limit::ProcessCpuTime(cpu_time));
+            }
+        }
+        if let Some(memory) = self.limits.process_memory {
+            if resident_memory().saturating_sub(self.memory_start) > memory {
+                return Some(Limit::ProcessMemory(memory));
+            }
+        }
+        None
+    }
+}
+
+/// CPU time consumed by all threads of the host process
+fn process_cpu_time() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `time` is a valid, writable timespec
+    if unsafe { libc::clock_gettime(libc::CLOCK_PROCESS_CPUTIME_ID, &mut time) } != 0 {
+        return Duration::ZERO;
+    }
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+/// Resident set size of the host process in bytes
+fn resident_memory() -> u64 {
+    let pages = std::fs::read_to_string("/proc/self/statm")
+        .ok()
+        .and_then(|statm| statm.split_whitespace().nth(1)?.parse::<u64>().ok())
+        .unwrap_or(0);
+    // SAFETY: sysconf has no memory-safety preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    pages * u64::try_from(page_size).unwrap_or(4096)
+}
@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Current lockfile format version
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Header written at the top of every lockfile
+const LOCKFILE_HEADER: &str =
+    "# This file is generated by hyperlight-nanvix. Commit it to make runs reproducible.\n";
+
+/// A binary pinned to an exact registry release
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// Registry package name, e.g. `"python"`
+    pub name: String,
+    /// Binary provided by the package, e.g. `"python3"`
+    pub binary: String,
+    /// Machine/deployment flavor, e.g. `"hyperlight/single-process"`
+    pub flavor: String,
+    /// Release identifier of the cache directory the binary was taken from
+    pub release: String,
+    /// Hex-encoded SHA-256 digest of the binary
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LockfileContents {
+    version: u32,
+    #[serde(default, rename = "package")]
+    packages: Vec<LockedPackage>,
+}
+
+/// Record of the exact releases and hashes used by previous runs.
+///
+/// Binaries listed in the lockfile are always taken from the recorded release
+/// and checked against the recorded hash. Binaries that are not listed yet are
+/// resolved normally and appended, similar to `Cargo.lock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    path: PathBuf,
+    packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    /// Load the lockfile at `path`, or start an empty one if it does not exist yet
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        let packages = match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let contents: LockfileContents =
+                    toml::from_str(&text).map_err(|e| invalid(&path, e))?;
+                if contents.version != LOCKFILE_VERSION {
+                    return Err(invalid(
+                        &path,
+                        format!("unsupported version {}", contents.version),
+                    ));
+                }
+                contents.packages
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, packages })
+    }
+
+    /// Path the lockfile is read from and written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All locked packages
+    pub fn packages(&self) -> &[LockedPackage] {
+        &self.packages
+    }
+
+    /// Find the entry for a binary of the given flavor
+    pub fn find(&self, flavor: &str, binary: &str) -> Option<&LockedPackage> {
+        self.packages
+            .iter()
+            .find(|package| package.flavor == flavor && package.binary == binary)
+    }
+
+    /// Add or replace the entry for a binary and write the lockfile to disk
+    pub fn record(&mut self, package: LockedPackage) -> Result<()> {
+        self.packages.retain(|existing| {
+            existing.flavor != package.flavor || existing.binary != package.binary
+        });
+        self.packages.push(package);
+        self.packages.sort_by(|a, b| {
+            (a.flavor.as_str(), a.binary.as_str()).cmp(&(b.flavor.as_str(), b.binary.as_str()))
+        });
+        self.save()
+    }
+
+    /// Write the lockfile to disk
+    pub fn save(&self) -> Result<()> {
+        let contents = LockfileContents {
+            version: LOCKFILE_VERSION,
+            packages: self.packages.clone(),
+        };
+        let text = toml::to_string(&contents).map_err(|e| invalid(&self.path, e))?;
+        std::fs::write(&self.path, format!("{}{}", LOCKFILE_HEADER, text))?;
+        Ok(())
+    }
+}
+
+fn invalid<E: std::fmt::Display>(path: &Path, reason: E) -> Error {
+    Error::InvalidLockfile {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    }
+}
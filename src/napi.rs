@@ -1,12 +1,18 @@
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
-use crate::runtime::{Runtime, RuntimeConfig};
+use crate::audit::JsonLinesSink;
+use crate::policy::SyscallPolicy;
+use crate::pool::{PoolConfig, SandboxPool};
+use crate::runtime::{CancelHandle, RunOutput, RuntimeConfig, WorkloadType};
+use crate::Sandbox;
 
-/// Node.js wrapper for hyperlight-nanvix Runtime
+/// Node.js wrapper for hyperlight-nanvix Sandbox, runs on it reuse its guests
+/// and wait for each other
 #[napi]
 pub struct NanvixSandbox {
-    runtime: Runtime,
+    sandbox: tokio::sync::Mutex<Sandbox>,
+    cancel: CancelHandle,
 }
 
 /// Configuration options for creating a sandbox
@@ -16,6 +22,93 @@ pub struct SandboxConfig {
     pub log_directory: Option<String>,
     /// Directory for temporary files
     pub tmp_directory: Option<String>,
+    /// Maximum run time in milliseconds before the guest is torn down
+    pub timeout_ms: Option<u32>,
+    /// Never download from the network registry
+    pub offline: Option<bool>,
+    /// Directory holding the nanvix-registry cache
+    pub cache_directory: Option<String>,
+    /// File holding the digests of cached binaries, e.g. one shipped with a read-only cache
+    pub digest_file: Option<String>,
+    /// Maximum memory the host process may allocate during a run, in MiB
+    pub process_memory_limit_mb: Option<u32>,
+    /// Maximum CPU time the host process may consume during a run, in milliseconds
+    pub process_cpu_time_limit_ms: Option<u32>,
+    /// Maximum console output a run may produce, in bytes
+    pub output_limit_bytes: Option<u32>,
+    /// TOML or JSON file with a syscall policy restricting the guest
+    pub policy_file: Option<String>,
+    /// File receiving a JSON line for every guest `openat`, the only syscall
+    /// this nanvix release intercepts
+    pub audit_file: Option<String>,
+}
+
+/// Options for a pool of pre-warmed sandboxes
+#[napi(object)]
+pub struct PoolOptions {
+    /// Number of warm guests kept per workload type
+    pub size: Option<u32>,
+    /// Number of runs after which a guest is replaced by a fresh one
+    pub max_reuse: Option<u32>,
+    /// Guests idle for longer than this many milliseconds are evicted
+    pub idle_timeout_ms: Option<u32>,
+    /// Workload types to keep warm guests for, e.g. `["javascript", "python"]`
+    pub workload_types: Option<Vec<String>>,
+}
+
+/// Build a runtime configuration from the JavaScript sandbox options
+fn runtime_config(config: Option<SandboxConfig>) -> Result<RuntimeConfig> {
+    let Some(cfg) = config else {
+        return Ok(RuntimeConfig::new());
+    };
+    let mut runtime_config = RuntimeConfig::new();
+    if let Some(log_dir) = cfg.log_directory {
+        runtime_config = runtime_config.with_log_directory(log_dir);
+    }
+    if let Some(tmp_dir) = cfg.tmp_directory {
+        runtime_config = runtime_config.with_tmp_directory(tmp_dir);
+    }
+    if let Some(timeout_ms) = cfg.timeout_ms {
+        runtime_config =
+            runtime_config.with_timeout(std::time::Duration::from_millis(timeout_ms.into()));
+    }
+    if let Some(offline) = cfg.offline {
+        runtime_config = runtime_config.with_offline(offline);
+    }
+    if let Some(cache_dir) = cfg.cache_directory {
+        runtime_config = runtime_config.with_cache_directory(cache_dir);
+    }
+    if let Some(digest_file) = cfg.digest_file {
+        runtime_config = runtime_config.with_digest_file(digest_file);
+    }
+    if let Some(memory_limit_mb) = cfg.process_memory_limit_mb {
+        runtime_config = runtime_config.with_process_memory_limit(u64::from(memory_limit_mb) << 20);
+    }
+    if let Some(cpu_time_limit_ms) = cfg.process_cpu_time_limit_ms {
+        runtime_config = runtime_config.with_process_cpu_time_limit(
+            std::time::Duration::from_millis(cpu_time_limit_ms.into()),
+        );
+    }
+    if let Some(output_limit_bytes) = cfg.output_limit_bytes {
+        runtime_config = runtime_config.with_output_limit(output_limit_bytes.into());
+    }
+    if let Some(policy_file) = cfg.policy_file {
+        let policy = SyscallPolicy::from_file(&policy_file)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+        runtime_config = runtime_config.with_syscall_policy(policy);
+    }
+    if let Some(audit_file) = cfg.audit_file {
+        let sink = JsonLinesSink::create(&audit_file)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+        runtime_config = runtime_config.with_syscall_audit(sink);
+    }
+    Ok(runtime_config)
+}
+
+/// Parse a workload type name, reporting failures as invalid arguments
+fn parse_workload_type(name: &str) -> Result<WorkloadType> {
+    name.parse()
+        .map_err(|e: crate::Error| Error::new(Status::InvalidArg, e.to_string()))
 }
 
 /// Workload execution result
@@ -23,6 +116,44 @@ pub struct SandboxConfig {
 pub struct WorkloadResult {
     pub success: bool,
     pub error: Option<String>,
+    /// Machine-readable kind of failure, e.g. `"WORKLOAD_NOT_FOUND"` or `"GUEST_EXIT"`
+    pub error_code: Option<String>,
+    /// Console output captured from the guest, both of its streams interleaved
+    pub console: String,
+    /// Exit status reported by the guest, absent if nanvix did not report one
+    pub exit_code: Option<i32>,
+    /// Wall-clock duration of the run in milliseconds
+    pub duration_ms: f64,
+}
+
+impl WorkloadResult {
+    fn from_run(result: crate::Result<RunOutput>) -> Self {
+        match result {
+            Ok(output) => {
+                let failure = output
+                    .exit_status
+                    .code()
+                    .filter(|code| *code != 0)
+                    .map(crate::Error::GuestExit);
+                Self {
+                    success: output.success(),
+                    error: failure.as_ref().map(|e| e.to_string()),
+                    error_code: failure.as_ref().map(|e| e.error_code().to_string()),
+                    exit_code: output.exit_status.code(),
+                    duration_ms: output.duration.as_secs_f64() * 1000.0,
+                    console: output.console,
+                }
+            }
+            Err(e) => Self {
+                success: false,
+                error: Some(format!("Workload execution failed: {}", e)),
+                error_code: Some(e.error_code().to_string()),
+                console: String::new(),
+                exit_code: None,
+                duration_ms: 0.0,
+            },
+        }
+    }
 }
 
 #[napi]
@@ -30,48 +161,174 @@ impl NanvixSandbox {
     /// Create a new sandbox instance
     #[napi(constructor)]
     pub fn new(config: Option<SandboxConfig>) -> Result<Self> {
-        let runtime_config = match config {
-            Some(cfg) => {
-                let mut runtime_config = RuntimeConfig::new();
-                if let Some(log_dir) = cfg.log_directory {
-                    runtime_config = runtime_config.with_log_directory(log_dir);
-                }
-                if let Some(tmp_dir) = cfg.tmp_directory {
-                    runtime_config = runtime_config.with_tmp_directory(tmp_dir);
-                }
-                runtime_config
-            }
-            None => RuntimeConfig::new(),
-        };
+        let runtime_config = runtime_config(config)?;
 
-        let runtime = Runtime::new(runtime_config)
-            .map_err(|e| Error::from_reason(format!("Failed to create runtime: {}", e)))?;
+        let sandbox = Sandbox::new(runtime_config)
+            .map_err(|e| Error::from_reason(format!("Failed to create sandbox: {}", e)))?;
 
-        Ok(Self { runtime })
+        Ok(Self {
+            cancel: sandbox.cancel_handle(),
+            sandbox: tokio::sync::Mutex::new(sandbox),
+        })
     }
 
-    /// Run a workload in the sandbox
+    /// Run a workload in the sandbox, optionally passing command-line arguments to it
     #[napi]
-    pub async fn run(&self, workload_path: String) -> Result<WorkloadResult> {
-        // Run the workload using the existing runtime
-        match self.runtime.run(&workload_path).await {
-            Ok(()) => Ok(WorkloadResult {
-                success: true,
-                error: None,
-            }),
-            Err(e) => Ok(WorkloadResult {
-                success: false,
-                error: Some(format!("Workload execution failed: {}", e)),
-            }),
-        }
+    pub async fn run(
+        &self,
+        workload_path: String,
+        args: Option<Vec<String>>,
+    ) -> Result<WorkloadResult> {
+        let args = args.unwrap_or_default();
+
+        // Run the workload, reusing the sandbox's guest for its workload type
+        let result = self
+            .sandbox
+            .lock()
+            .await
+            .run_with_args(&workload_path, &args)
+            .await;
+        Ok(WorkloadResult::from_run(result))
+    }
+
+    /// Run inline source code in the sandbox
+    ///
+    /// `workloadType` is either `"javascript"` or `"python"`.
+    #[napi]
+    pub async fn run_code(&self, workload_type: String, code: String) -> Result<WorkloadResult> {
+        let workload_type = parse_workload_type(&workload_type)?;
+
+        let result = self
+            .sandbox
+            .lock()
+            .await
+            .run_source(workload_type, &code)
+            .await;
+        Ok(WorkloadResult::from_run(result))
+    }
+
+    /// Cancel the workload currently running in this sandbox
+    #[napi]
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Snapshot a booted guest so later runs of `workloadType` skip kernel boot
+    /// and interpreter start-up, returns the path of the snapshot
+    #[napi]
+    pub async fn snapshot(&self, workload_type: String) -> Result<String> {
+        let workload_type = parse_workload_type(&workload_type)?;
+
+        self.sandbox
+            .lock()
+            .await
+            .snapshot(workload_type)
+            .await
+            .map(|path| path.to_string_lossy().into_owned())
+            .map_err(|e| Error::from_reason(format!("Failed to take snapshot: {}", e)))
     }
 
     /// Clear the binary cache
     #[napi]
     pub async fn clear_cache(&self) -> Result<bool> {
-        match self.runtime.clear_cache().await {
+        match self.sandbox.lock().await.clear_cache().await {
             Ok(()) => Ok(true),
             Err(e) => Err(Error::from_reason(format!("Failed to clear cache: {}", e))),
         }
     }
 }
+
+/// Node.js wrapper for a pool of pre-warmed hyperlight-nanvix sandboxes
+#[napi]
+pub struct NanvixSandboxPool {
+    pool: SandboxPool,
+}
+
+#[napi]
+impl NanvixSandboxPool {
+    /// Create a new pool, call `warm()` to boot its guests ahead of the first run
+    #[napi(constructor)]
+    pub fn new(config: Option<SandboxConfig>, options: Option<PoolOptions>) -> Result<Self> {
+        let mut pool_config = PoolConfig::new();
+        if let Some(options) = options {
+            if let Some(size) = options.size {
+                pool_config = pool_config.with_size(size as usize);
+            }
+            if let Some(max_reuse) = options.max_reuse {
+                pool_config = pool_config.with_max_reuse(max_reuse);
+            }
+            if let Some(idle_timeout_ms) = options.idle_timeout_ms {
+                pool_config = pool_config.with_idle_timeout(Some(
+                    std::time::Duration::from_millis(idle_timeout_ms.into()),
+                ));
+            }
+            if let Some(workload_types) = options.workload_types {
+                let workload_types = workload_types
+                    .iter()
+                    .map(|name| parse_workload_type(name))
+                    .collect::<Result<Vec<_>>>()?;
+                pool_config = pool_config.with_workload_types(workload_types);
+            }
+        }
+
+        let pool = SandboxPool::new(runtime_config(config)?, pool_config)
+            .map_err(|e| Error::from_reason(format!("Failed to create pool: {}", e)))?;
+
+        Ok(Self { pool })
+    }
+
+    /// Boot guests until every configured workload type has its warm guests
+    #[napi]
+    pub async fn warm(&self) -> Result<()> {
+        self.pool
+            .warm()
+            .await
+            .map_err(|e| Error::from_reason(format!("Failed to warm pool: {}", e)))
+    }
+
+    /// Run a workload on a warm guest, optionally passing command-line arguments to it
+    #[napi]
+    pub async fn run(
+        &self,
+        workload_path: String,
+        args: Option<Vec<String>>,
+    ) -> Result<WorkloadResult> {
+        let args = args.unwrap_or_default();
+        let result = self.pool.run_with_args(&workload_path, &args).await;
+        Ok(WorkloadResult::from_run(result))
+    }
+
+    /// Run inline source code on a warm guest
+    ///
+    /// `workloadType` is either `"javascript"` or `"python"`.
+    #[napi]
+    pub async fn run_code(&self, workload_type: String, code: String) -> Result<WorkloadResult> {
+        let workload_type = parse_workload_type(&workload_type)?;
+        let result = self.pool.run_source(workload_type, &code).await;
+        Ok(WorkloadResult::from_run(result))
+    }
+
+    /// Cancel the workloads currently running in this pool
+    #[napi]
+    pub fn cancel(&self) {
+        self.pool.cancel_handle().cancel();
+    }
+
+    /// Number of idle warm guests
+    #[napi(getter)]
+    pub fn idle_count(&self) -> u32 {
+        self.pool.idle_count() as u32
+    }
+
+    /// Tear down guests idle for longer than the idle timeout, returns how many were evicted
+    #[napi]
+    pub fn evict_idle(&self) -> u32 {
+        self.pool.evict_idle() as u32
+    }
+
+    /// Tear down every idle guest
+    #[napi]
+    pub fn shutdown(&self) {
+        self.pool.shutdown();
+    }
+}
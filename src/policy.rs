@@ -0,0 +1,364 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Syscalls the runtime can intercept with the syscall tables of this nanvix release.
+///
+/// These are all the entries the pinned nanvix `SyscallTable` exposes. Other
+/// syscalls, such as `unlink`, `read` or `write`, have no entry the host can
+/// replace, so they cannot be governed, faulted or recorded.
+pub const INTERCEPTABLE_SYSCALLS: &[&str] = &["openat"];
+
+/// Error numbers that can be named in policies, e.g. `"EPERM"`
+const ERRNO_NAMES: &[(&str, i32)] = &[
+    ("EPERM", libc::EPERM),
+    ("ENOENT", libc::ENOENT),
+    ("EIO", libc::EIO),
+    ("EBADF", libc::EBADF),
+    ("EAGAIN", libc::EAGAIN),
+    ("ENOMEM", libc::ENOMEM),
+    ("EACCES", libc::EACCES),
+    ("EBUSY", libc::EBUSY),
+    ("EEXIST", libc::EEXIST),
+    ("ENOTDIR", libc::ENOTDIR),
+    ("EISDIR", libc::EISDIR),
+    ("EINVAL", libc::EINVAL),
+    ("EMFILE", libc::EMFILE),
+    ("ENOSPC", libc::ENOSPC),
+    ("EROFS", libc::EROFS),
+    ("ENAMETOOLONG", libc::ENAMETOOLONG),
+    ("ENOSYS", libc::ENOSYS),
+];
+
+/// Look up an error number by its name, e.g. `"ENOENT"`
+pub fn errno_from_name(name: &str) -> Option<i32> {
+    ERRNO_NAMES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|(_, errno)| *errno)
+}
+
+/// Name of an error number, e.g. `"ENOENT"`
+pub fn errno_name(errno: i32) -> Option<&'static str> {
+    ERRNO_NAMES
+        .iter()
+        .find(|(_, known)| *known == errno)
+        .map(|(name, _)| *name)
+}
+
+/// What happens to a syscall matched by a policy rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PolicyAction {
+    /// Perform the syscall
+    Allow,
+    /// Fail the syscall with `EPERM`
+    Deny,
+    /// Fail the syscall with the given error number
+    Errno(i32),
+}
+
+impl PolicyAction {
+    /// Error number the syscall fails with, `None` if it is allowed
+    pub fn errno(&self) -> Option<i32> {
+        match self {
+            PolicyAction::Allow => None,
+            PolicyAction::Deny => Some(libc::EPERM),
+            PolicyAction::Errno(errno) => Some(*errno),
+        }
+    }
+}
+
+impl std::str::FromStr for PolicyAction {
+    type Err = Error;
+
+    /// Parse `"allow"`, `"deny"` or an error name such as `"ENOENT"`
+    fn from_str(action: &str) -> Result<Self> {
+        match action.to_lowercase().as_str() {
+            "allow" => Ok(PolicyAction::Allow),
+            "deny" => Ok(PolicyAction::Deny),
+            _ => errno_from_name(action)
+                .map(PolicyAction::Errno)
+                .ok_or_else(|| {
+                    Error::InvalidPolicy(format!(
+                        "unknown action {:?}, expected allow, deny or an error name such as EPERM",
+                        action
+                    ))
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for PolicyAction {
+    type Error = Error;
+
+    fn try_from(action: String) -> Result<Self> {
+        action.parse()
+    }
+}
+
+impl From<PolicyAction> for String {
+    fn from(action: PolicyAction) -> Self {
+        action.to_string()
+    }
+}
+
+impl std::fmt::Display for PolicyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyAction::Allow => write!(f, "allow"),
+            PolicyAction::Deny => write!(f, "deny"),
+            PolicyAction::Errno(errno) => match errno_name(*errno) {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "errno {}", errno),
+            },
+        }
+    }
+}
+
+/// Kind of access a syscall requests on a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Access {
+    /// The path is only read
+    ReadOnly,
+    /// The path may be created, written or truncated
+    ReadWrite,
+}
+
+impl Access {
+    /// Access requested by `openat` flags
+    pub fn from_open_flags(flags: i32) -> Self {
+        let writes = flags & libc::O_ACCMODE != libc::O_RDONLY;
+        if writes || flags & (libc::O_CREAT | libc::O_TRUNC) != 0 {
+            Access::ReadWrite
+        } else {
+            Access::ReadOnly
+        }
+    }
+}
+
+/// A single rule of a [`SyscallPolicy`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// Syscall the rule applies to, e.g. `"openat"`
+    pub syscall: String,
+    /// Action taken when the rule matches
+    pub action: PolicyAction,
+    /// Only match paths at or below this host directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Only match requests that need at most this access
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access: Option<Access>,
+}
+
+impl PolicyRule {
+    pub fn new<S: Into<String>>(syscall: S, action: PolicyAction) -> Self {
+        Self {
+            syscall: syscall.into(),
+            action,
+            path: None,
+            access: None,
+        }
+    }
+
+    /// Allow a syscall
+    pub fn allow<S: Into<String>>(syscall: S) -> Self {
+        Self::new(syscall, PolicyAction::Allow)
+    }
+
+    /// Fail a syscall with `EPERM`
+    pub fn deny<S: Into<String>>(syscall: S) -> Self {
+        Self::new(syscall, PolicyAction::Deny)
+    }
+
+    /// Only match paths at or below a host directory
+    pub fn under<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Only match requests that do not create, write or truncate the path
+    pub fn read_only(mut self) -> Self {
+        self.access = Some(Access::ReadOnly);
+        self
+    }
+
+    fn matches(&self, syscall: &str, path: Option<&Path>, access: Access) -> bool {
+        if self.syscall != syscall {
+            return false;
+        }
+        if let Some(prefix) = &self.path {
+            match path {
+                Some(path) if path.starts_with(prefix) => {}
+                _ => return false,
+            }
+        }
+        match self.access {
+            Some(Access::ReadOnly) => access == Access::ReadOnly,
+            _ => true,
+        }
+    }
+}
+
+/// A declarative policy deciding which guest syscalls are performed.
+///
+/// Rules are checked in order and the first matching rule decides; syscalls
+/// that match no rule get the default action. Policies can be built in Rust
+/// or loaded from TOML or JSON:
+///
+/// ```toml
+/// default = "EPERM"
+///
+/// [[rule]]
+/// syscall = "openat"
+/// path = "/data"
+/// access = "read-only"
+/// action = "allow"
+/// ```
+///
+/// Only the syscalls in [`INTERCEPTABLE_SYSCALLS`] can be governed by a
+/// policy; rules naming other syscalls are rejected when the policy is built
+/// or parsed, and again when a runtime is created with it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallPolicy {
+    /// Action for syscalls that match no rule
+    #[serde(default = "default_action")]
+    pub default: PolicyAction,
+    /// Rules, checked in order
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<PolicyRule>,
+}
+
+fn default_action() -> PolicyAction {
+    PolicyAction::Allow
+}
+
+impl Default for SyscallPolicy {
+    fn default() -> Self {
+        Self::new(PolicyAction::Allow)
+    }
+}
+
+impl SyscallPolicy {
+    /// Create an empty policy that applies `default` to every syscall
+    pub fn new(default: PolicyAction) -> Self {
+        Self {
+            default,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Append a rule, checked after the rules added before it
+    pub fn with_rule(mut self, rule: PolicyRule) -> Result<Self> {
+        validate_rule(&rule)?;
+        self.rules.push(rule);
+        Ok(self)
+    }
+
+    /// Parse a policy from TOML
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let policy: Self = toml::from_str(text).map_err(|e| Error::InvalidPolicy(e.to_string()))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Parse a policy from JSON
+    pub fn from_json_str(text: &str) -> Result<Self> {
+        let policy: Self =
+            serde_json::from_str(text).map_err(|e| Error::InvalidPolicy(e.to_string()))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Load a policy from a `.json` file, or a TOML file otherwise
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let policy = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            Self::from_json_str(&text)
+        } else {
+            Self::from_toml_str(&text)
+        };
+        policy.map_err(|e| match e {
+            Error::InvalidPolicy(reason) => {
+                Error::InvalidPolicy(format!("{}: {}", path.display(), reason))
+            }
+            e => e,
+        })
+    }
+
+    /// Decide what happens to a syscall.
+    ///
+    /// `path` is matched lexically after resolving `.` and `..` components.
+    pub fn evaluate(&self, syscall: &str, path: Option<&Path>, access: Access) -> PolicyAction {
+        let path = path.map(normalize_path);
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(syscall, path.as_deref(), access))
+            .map_or(self.default, |rule| rule.action)
+    }
+
+    /// Check every rule, including rules pushed to [`SyscallPolicy::rules`] directly
+    pub fn validate(&self) -> Result<()> {
+        self.rules.iter().try_for_each(validate_rule)
+    }
+}
+
+fn validate_rule(rule: &PolicyRule) -> Result<()> {
+    if !INTERCEPTABLE_SYSCALLS.contains(&rule.syscall.as_str()) {
+        return Err(Error::InvalidPolicy(format!(
+            "syscall {:?} cannot be intercepted, the nanvix syscall table only exposes: {}",
+            rule.syscall,
+            INTERCEPTABLE_SYSCALLS.join(", ")
+        )));
+    }
+    if let Some(path) = rule.path.as_ref().filter(|path| !path.is_absolute()) {
+        return Err(Error::InvalidPolicy(format!(
+            "rule path {:?} must be absolute",
+            path
+        )));
+    }
+    Ok(())
+}
+
+/// Resolve `.` and `..` components without touching the filesystem
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Canonicalize the longest existing ancestor of a path and append the rest.
+///
+/// The ancestor is canonicalized as written, so a `..` following a symbolic
+/// link leaves the link's target like it does when the kernel opens the path.
+/// Only the part that does not exist yet is normalized lexically, on top of
+/// the symlink-free canonical ancestor.
+pub(crate) fn canonical_or_normalized(path: &Path) -> PathBuf {
+    let mut existing = path;
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let rest = path.strip_prefix(existing).unwrap_or(Path::new(""));
+            return normalize_path(&canonical.join(rest));
+        }
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => return normalize_path(path),
+        }
+    }
+}
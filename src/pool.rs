@@ -0,0 +1,290 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use nanvix::log;
+
+use crate::error::Result;
+use crate::runtime::{CancelHandle, Guest, RunOutput, Runtime, RuntimeConfig, WorkloadType};
+
+/// Configuration of a [`SandboxPool`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Number of warm guests kept per workload type
+    pub size: usize,
+    /// Number of runs after which a guest is replaced by a fresh one
+    pub max_reuse: u32,
+    /// Idle guests older than this are evicted instead of reused
+    pub idle_timeout: Option<Duration>,
+    /// Workload types to keep warm guests for
+    pub workload_types: Vec<WorkloadType>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            size: 2,
+            max_reuse: 100,
+            idle_timeout: Some(Duration::from_secs(300)),
+            workload_types: vec![WorkloadType::JavaScript, WorkloadType::Python],
+        }
+    }
+}
+
+impl PoolConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep this many warm guests per workload type
+    pub fn with_size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Replace a guest with a fresh one after this many runs
+    pub fn with_max_reuse(mut self, max_reuse: u32) -> Self {
+        self.max_reuse = max_reuse.max(1);
+        self
+    }
+
+    /// Evict guests that have been idle for longer than this, `None` to keep them forever
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Only keep warm guests for these workload types
+    pub fn with_workload_types(mut self, workload_types: Vec<WorkloadType>) -> Self {
+        self.workload_types = workload_types;
+        self
+    }
+}
+
+/// A pool of pre-warmed guests for low-latency repeated runs.
+///
+/// Every guest has its kernel booted and interpreter loaded by a warm-up run.
+/// Each run takes an idle guest of the matching workload type (or prepares a
+/// new one if none is available) and hands it back afterwards, until it
+/// reaches `max_reuse` workload runs; the warm-up run does not count. Guests whose run failed are torn down and
+/// replaced in the background. Binary workloads bypass the pool.
+///
+/// Cloning the pool is cheap, clones share the same guests.
+#[derive(Clone)]
+pub struct SandboxPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    runtime: Runtime,
+    config: PoolConfig,
+    idle: Mutex<HashMap<WorkloadType, VecDeque<Guest>>>,
+}
+
+impl SandboxPool {
+    /// Create a pool, guests are only prepared by [`SandboxPool::warm`] or on first use
+    pub fn new(runtime_config: RuntimeConfig, pool_config: PoolConfig) -> Result<Self> {
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                runtime: Runtime::new(runtime_config)?,
+                config: pool_config,
+                idle: Mutex::new(HashMap::new()),
+            }),
+        })
+    }
+
+    /// Configuration of the pool
+    pub fn config(&self) -> &PoolConfig {
+        &self.inner.config
+    }
+
+    /// Boot guests until every configured workload type has `size` idle guests
+    pub async fn warm(&self) -> Result<()> {
+        for &workload_type in &self.inner.config.workload_types {
+            while self.inner.idle_count(workload_type) < self.inner.config.size {
+                let guest = self.inner.boot(workload_type).await?;
+                self.inner.release(guest);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a workload on a warm guest and capture its output
+    pub async fn run<P: AsRef<Path>>(&self, workload_path: P) -> Result<RunOutput> {
+        self.run_with_args(workload_path, &[] as &[&str]).await
+    }
+
+    /// Run a workload with additional command-line arguments on a warm guest
+    pub async fn run_with_args<P: AsRef<Path>, S: AsRef<str>>(
+        &self,
+        workload_path: P,
+        args: &[S],
+    ) -> Result<RunOutput> {
+        let workload_path = workload_path.as_ref();
+        let workload_type = Runtime::resolve_workload(workload_path)?;
+        if workload_type == WorkloadType::Binary {
+            return self.inner.runtime.run_with_args(workload_path, args).await;
+        }
+
+        let mut guest = match self.inner.acquire(workload_type) {
+            Some(guest) => guest,
+            None => self.inner.runtime.prepare_guest(workload_type).await?,
+        };
+        let result = self
+            .inner
+            .runtime
+            .execute(&mut guest, workload_path, args)
+            .await;
+
+        if result.is_ok() && guest.runs() < self.inner.config.max_reuse {
+            self.inner.release(guest);
+        } else {
+            // Tear the guest down and boot a replacement in the background
+            drop(guest);
+            self.replenish(workload_type);
+        }
+        result
+    }
+
+    /// Run inline source code on a warm guest
+    pub async fn run_source(&self, workload_type: WorkloadType, source: &str) -> Result<RunOutput> {
+        let source_file = self.inner.runtime.stage_source(workload_type, source)?;
+        self.run(&source_file.0).await
+    }
+
+    /// Get a handle that cancels the runs of every guest in the pool
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.inner.runtime.cancel_handle()
+    }
+
+    /// Number of idle guests across all workload types
+    pub fn idle_count(&self) -> usize {
+        self.inner
+            .idle
+            .lock()
+            .unwrap()
+            .values()
+            .map(VecDeque::len)
+            .sum()
+    }
+
+    /// Number of guests prepared since the pool was created, a run on a
+    /// reused guest does not prepare a new one
+    #[doc(hidden)]
+    pub fn guests_prepared(&self) -> usize {
+        self.inner.runtime.guests_prepared()
+    }
+
+    /// Tear down guests that have been idle for longer than the idle timeout.
+    ///
+    /// Expired guests are also evicted whenever a run looks for an idle guest.
+    /// Returns the number of evicted guests.
+    pub fn evict_idle(&self) -> usize {
+        let expired: Vec<Guest> = self
+            .inner
+            .idle
+            .lock()
+            .unwrap()
+            .values_mut()
+            .flat_map(|guests| self.inner.take_expired(guests))
+            .collect();
+        expired.len()
+    }
+
+    /// Tear down every idle guest, running workloads are not affected
+    pub fn shutdown(&self) {
+        let drained: Vec<Guest> = self
+            .inner
+            .idle
+            .lock()
+            .unwrap()
+            .drain()
+            .flat_map(|(_, guests)| guests)
+            .collect();
+        drop(drained);
+    }
+
+    /// Boot a replacement guest without holding up the caller
+    fn replenish(&self, workload_type: WorkloadType) {
+        if !self.inner.config.workload_types.contains(&workload_type)
+            || tokio::runtime::Handle::try_current().is_err()
+        {
+            return;
+        }
+        let inner = Arc::clone(&self.inner);
+        tokio::spawn(async move {
+            if inner.idle_count(workload_type) >= inner.config.size {
+                return;
+            }
+            match inner.boot(workload_type).await {
+                Ok(guest) => inner.release(guest),
+                Err(e) => log::warn!("Failed to boot replacement guest: {}", e),
+            }
+        });
+    }
+}
+
+impl PoolInner {
+    fn idle_count(&self, workload_type: WorkloadType) -> usize {
+        self.idle
+            .lock()
+            .unwrap()
+            .get(&workload_type)
+            .map_or(0, VecDeque::len)
+    }
+
+    /// Prepare a guest and boot it with an empty warm-up workload
+    async fn boot(&self, workload_type: WorkloadType) -> Result<Guest> {
+        let mut guest = self.runtime.prepare_guest(workload_type).await?;
+        let warm_up = self.runtime.stage_source(workload_type, "")?;
+        self.runtime
+            .execute(&mut guest, &warm_up.0, &[] as &[&str])
+            .await?;
+        // Only workload runs count towards max_reuse
+        guest.reset_runs();
+        Ok(guest)
+    }
+
+    /// Take the most recently used idle guest of a workload type
+    fn acquire(&self, workload_type: WorkloadType) -> Option<Guest> {
+        // Expired guests are torn down once the lock is released
+        let (guest, _expired) = {
+            let mut idle = self.idle.lock().unwrap();
+            let guests = idle.get_mut(&workload_type)?;
+            let expired = self.take_expired(guests);
+            (guests.pop_back(), expired)
+        };
+        guest
+    }
+
+    /// Hand a guest back, tearing it down if the pool is already full
+    fn release(&self, guest: Guest) {
+        let surplus = {
+            let mut idle = self.idle.lock().unwrap();
+            let guests = idle.entry(guest.workload_type()).or_default();
+            if guests.len() < self.config.size {
+                guests.push_back(guest);
+                None
+            } else {
+                Some(guest)
+            }
+        };
+        // Tearing a guest down can take a while, so other runs are not held up
+        drop(surplus);
+    }
+
+    /// Remove the guests idle for longer than the idle timeout.
+    ///
+    /// The caller drops them after releasing the lock on the idle guests.
+    fn take_expired(&self, guests: &mut VecDeque<Guest>) -> Vec<Guest> {
+        let Some(idle_timeout) = self.config.idle_timeout else {
+            return Vec::new();
+        };
+        let (expired, fresh) = std::mem::take(guests)
+            .into_iter()
+            .partition(|guest| guest.idle_for() > idle_timeout);
+        *guests = fresh;
+        expired
+    }
+}
@@ -1,15 +1,67 @@
 #![allow(non_local_definitions)]
 
-use pyo3::exceptions::PyRuntimeError;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
 use pyo3::prelude::*;
 use std::sync::Arc;
+use tokio::sync::Mutex;
 
-use crate::runtime::{Runtime, RuntimeConfig};
+use crate::audit::JsonLinesSink;
+use crate::error::Error;
+use crate::policy::SyscallPolicy;
+use crate::pool::{PoolConfig as RuntimePoolConfig, SandboxPool};
+use crate::runtime::{CancelHandle, RunOutput, RuntimeConfig, WorkloadType};
+use crate::Sandbox;
 
-/// Python wrapper for hyperlight-nanvix Runtime
+create_exception!(
+    hyperlight_nanvix,
+    NanvixError,
+    PyException,
+    "Base class for all hyperlight-nanvix errors."
+);
+create_exception!(hyperlight_nanvix, UnknownWorkloadTypeError, NanvixError);
+create_exception!(hyperlight_nanvix, WorkloadNotFoundError, NanvixError);
+create_exception!(hyperlight_nanvix, InvalidWorkloadError, NanvixError);
+create_exception!(hyperlight_nanvix, RegistryUnavailableError, NanvixError);
+create_exception!(hyperlight_nanvix, InterpreterMissingError, NanvixError);
+create_exception!(hyperlight_nanvix, KernelMissingError, NanvixError);
+create_exception!(hyperlight_nanvix, NotCachedError, NanvixError);
+create_exception!(hyperlight_nanvix, IntegrityError, NanvixError);
+create_exception!(hyperlight_nanvix, GuestCrashedError, NanvixError);
+create_exception!(hyperlight_nanvix, GuestExitError, NanvixError);
+create_exception!(hyperlight_nanvix, WorkloadTimeoutError, NanvixError);
+create_exception!(hyperlight_nanvix, WorkloadCancelledError, NanvixError);
+create_exception!(hyperlight_nanvix, LimitExceededError, NanvixError);
+
+/// Map a hyperlight-nanvix error to the matching Python exception class
+fn to_py_err(error: Error) -> PyErr {
+    let message = error.to_string();
+    match error {
+        Error::UnknownWorkloadType(_) => UnknownWorkloadTypeError::new_err(message),
+        Error::WorkloadNotFound(_) => WorkloadNotFoundError::new_err(message),
+        Error::InvalidWorkload(_) => InvalidWorkloadError::new_err(message),
+        Error::RegistryUnavailable(_) => RegistryUnavailableError::new_err(message),
+        Error::InterpreterMissing(_) => InterpreterMissingError::new_err(message),
+        Error::KernelMissing => KernelMissingError::new_err(message),
+        Error::NotCached(_) => NotCachedError::new_err(message),
+        Error::IntegrityMismatch { .. } | Error::DigestMissing(_) => {
+            IntegrityError::new_err(message)
+        }
+        Error::GuestCrashed(_) => GuestCrashedError::new_err(message),
+        Error::GuestExit(_) => GuestExitError::new_err(message),
+        Error::Timeout(_) => WorkloadTimeoutError::new_err(message),
+        Error::Cancelled => WorkloadCancelledError::new_err(message),
+        Error::LimitExceeded(_) => LimitExceededError::new_err(message),
+        _ => NanvixError::new_err(message),
+    }
+}
+
+/// Python wrapper for hyperlight-nanvix Sandbox, runs on it reuse its guests
+/// and wait for each other
 #[pyclass]
 pub struct NanvixSandbox {
-    runtime: Arc<Runtime>,
+    sandbox: Arc<Mutex<Sandbox>>,
+    cancel: CancelHandle,
 }
 
 /// Configuration options for creating a sandbox
@@ -20,16 +72,137 @@ pub struct SandboxConfig {
     pub log_directory: Option<String>,
     #[pyo3(get, set)]
     pub tmp_directory: Option<String>,
+    #[pyo3(get, set)]
+    pub timeout: Option<f64>,
+    #[pyo3(get, set)]
+    pub offline: Option<bool>,
+    #[pyo3(get, set)]
+    pub cache_directory: Option<String>,
+    #[pyo3(get, set)]
+    pub digest_file: Option<String>,
+    #[pyo3(get, set)]
+    pub process_memory_limit: Option<u64>,
+    #[pyo3(get, set)]
+    pub process_cpu_time_limit: Option<f64>,
+    #[pyo3(get, set)]
+    pub output_limit: Option<u64>,
+    #[pyo3(get, set)]
+    pub policy_file: Option<String>,
+    #[pyo3(get, set)]
+    pub audit_file: Option<String>,
 }
 
 #[pymethods]
 impl SandboxConfig {
     #[new]
-    #[pyo3(signature = (log_directory=None, tmp_directory=None))]
-    fn new(log_directory: Option<String>, tmp_directory: Option<String>) -> Self {
+    #[pyo3(signature = (log_directory=None, tmp_directory=None, timeout=None, offline=None, cache_directory=None, process_memory_limit=None, process_cpu_time_limit=None, output_limit=None, policy_file=None, audit_file=None, digest_file=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        log_directory: Option<String>,
+        tmp_directory: Option<String>,
+        timeout: Option<f64>,
+        offline: Option<bool>,
+        cache_directory: Option<String>,
+        process_memory_limit: Option<u64>,
+        process_cpu_time_limit: Option<f64>,
+        output_limit: Option<u64>,
+        policy_file: Option<String>,
+        audit_file: Option<String>,
+        digest_file: Option<String>,
+    ) -> Self {
         Self {
             log_directory,
             tmp_directory,
+            timeout,
+            offline,
+            cache_directory,
+            digest_file,
+            process_memory_limit,
+            process_cpu_time_limit,
+            output_limit,
+            policy_file,
+            audit_file,
+        }
+    }
+}
+
+/// Build a runtime configuration from the Python sandbox options
+fn runtime_config(config: Option<SandboxConfig>) -> PyResult<RuntimeConfig> {
+    let Some(cfg) = config else {
+        return Ok(RuntimeConfig::new());
+    };
+    let mut runtime_config = RuntimeConfig::new();
+    if let Some(log_dir) = cfg.log_directory {
+        runtime_config = runtime_config.with_log_directory(log_dir);
+    }
+    if let Some(tmp_dir) = cfg.tmp_directory {
+        runtime_config = runtime_config.with_tmp_directory(tmp_dir);
+    }
+    if let Some(timeout) = cfg.timeout {
+        let timeout = std::time::Duration::try_from_secs_f64(timeout)
+            .map_err(|e| PyValueError::new_err(format!("Invalid timeout: {}", e)))?;
+        runtime_config = runtime_config.with_timeout(timeout);
+    }
+    if let Some(offline) = cfg.offline {
+        runtime_config = runtime_config.with_offline(offline);
+    }
+    if let Some(cache_dir) = cfg.cache_directory {
+        runtime_config = runtime_config.with_cache_directory(cache_dir);
+    }
+    if let Some(digest_file) = cfg.digest_file {
+        runtime_config = runtime_config.with_digest_file(digest_file);
+    }
+    if let Some(memory_limit) = cfg.process_memory_limit {
+        runtime_config = runtime_config.with_process_memory_limit(memory_limit);
+    }
+    if let Some(cpu_time_limit) = cfg.process_cpu_time_limit {
+        let cpu_time_limit = std::time::Duration::try_from_secs_f64(cpu_time_limit)
+            .map_err(|e| PyValueError::new_err(format!("Invalid CPU-time limit: {}", e)))?;
+        runtime_config = runtime_config.with_process_cpu_time_limit(cpu_time_limit);
+    }
+    if let Some(output_limit) = cfg.output_limit {
+        runtime_config = runtime_config.with_output_limit(output_limit);
+    }
+    if let Some(policy_file) = cfg.policy_file {
+        let policy = SyscallPolicy::from_file(policy_file).map_err(to_py_err)?;
+        runtime_config = runtime_config.with_syscall_policy(policy);
+    }
+    if let Some(audit_file) = cfg.audit_file {
+        let sink = JsonLinesSink::create(audit_file).map_err(to_py_err)?;
+        runtime_config = runtime_config.with_syscall_audit(sink);
+    }
+    Ok(runtime_config)
+}
+
+/// Configuration options for a pool of pre-warmed sandboxes
+#[pyclass]
+#[derive(Clone)]
+pub struct PoolConfig {
+    #[pyo3(get, set)]
+    pub size: Option<usize>,
+    #[pyo3(get, set)]
+    pub max_reuse: Option<u32>,
+    #[pyo3(get, set)]
+    pub idle_timeout: Option<f64>,
+    #[pyo3(get, set)]
+    pub workload_types: Option<Vec<String>>,
+}
+
+#[pymethods]
+impl PoolConfig {
+    #[new]
+    #[pyo3(signature = (size=None, max_reuse=None, idle_timeout=None, workload_types=None))]
+    fn new(
+        size: Option<usize>,
+        max_reuse: Option<u32>,
+        idle_timeout: Option<f64>,
+        workload_types: Option<Vec<String>>,
+    ) -> Self {
+        Self {
+            size,
+            max_reuse,
+            idle_timeout,
+            workload_types,
         }
     }
 }
@@ -42,10 +215,44 @@ pub struct WorkloadResult {
     pub success: bool,
     #[pyo3(get)]
     pub error: Option<String>,
+    #[pyo3(get)]
+    pub console: String,
+    #[pyo3(get)]
+    pub exit_code: Option<i32>,
+    #[pyo3(get)]
+    pub duration: f64,
+}
+
+impl From<RunOutput> for WorkloadResult {
+    fn from(output: RunOutput) -> Self {
+        Self {
+            success: output.success(),
+            error: output
+                .exit_status
+                .code()
+                .filter(|code| *code != 0)
+                .map(|code| Error::GuestExit(code).to_string()),
+            exit_code: output.exit_status.code(),
+            duration: output.duration.as_secs_f64(),
+            console: output.console,
+        }
+    }
 }
 
 #[pymethods]
 impl WorkloadResult {
+    /// Raise GuestExitError if the guest exited with a non-zero status
+    ///
+    /// Example:
+    ///     >>> result = await sandbox.run("script.py")
+    ///     >>> result.check_returncode()
+    fn check_returncode(&self) -> PyResult<()> {
+        match self.exit_code {
+            Some(code) if code != 0 => Err(to_py_err(Error::GuestExit(code))),
+            _ => Ok(()),
+        }
+    }
+
     fn __repr__(&self) -> String {
         match &self.error {
             Some(err) => format!("WorkloadResult(success={}, error='{}')", self.success, err),
@@ -59,7 +266,7 @@ impl NanvixSandbox {
     /// Create a new sandbox instance
     ///
     /// Args:
-    ///     config: Optional SandboxConfig with log_directory and tmp_directory
+    ///     config: Optional SandboxConfig with log_directory, tmp_directory and timeout
     ///
     /// Returns:
     ///     A new NanvixSandbox instance
@@ -71,25 +278,13 @@ impl NanvixSandbox {
     #[new]
     #[pyo3(signature = (config=None))]
     fn new(config: Option<SandboxConfig>) -> PyResult<Self> {
-        let runtime_config = match config {
-            Some(cfg) => {
-                let mut runtime_config = RuntimeConfig::new();
-                if let Some(log_dir) = cfg.log_directory {
-                    runtime_config = runtime_config.with_log_directory(log_dir);
-                }
-                if let Some(tmp_dir) = cfg.tmp_directory {
-                    runtime_config = runtime_config.with_tmp_directory(tmp_dir);
-                }
-                runtime_config
-            }
-            None => RuntimeConfig::new(),
-        };
+        let runtime_config = runtime_config(config)?;
 
-        let runtime = Runtime::new(runtime_config)
-            .map_err(|e| PyRuntimeError::new_err(format!("Failed to create runtime: {}", e)))?;
+        let sandbox = Sandbox::new(runtime_config).map_err(to_py_err)?;
 
         Ok(Self {
-            runtime: Arc::new(runtime),
+            cancel: sandbox.cancel_handle(),
+            sandbox: Arc::new(Mutex::new(sandbox)),
         })
     }
 
@@ -97,28 +292,106 @@ impl NanvixSandbox {
     ///
     /// Args:
     ///     workload_path: Path to the workload file (JavaScript, Python, or binary)
+    ///     args: Optional list of command-line arguments passed to the workload
     ///
     /// Returns:
-    ///     WorkloadResult indicating success or failure
+    ///     WorkloadResult with the captured output, exit code and duration
+    ///
+    /// Raises:
+    ///     NanvixError: A subclass such as WorkloadNotFoundError or
+    ///         WorkloadTimeoutError if the workload could not be run
     ///
     /// Example:
-    ///     >>> result = await sandbox.run("script.py")
+    ///     >>> result = await sandbox.run("script.py", ["--verbose"])
     ///     >>> if result.success:
-    ///     ...     print("Success!")
-    fn run<'py>(&self, py: Python<'py>, workload_path: String) -> PyResult<&'py PyAny> {
-        let runtime = Arc::clone(&self.runtime);
+    ///     ...     print(result.console)
+    #[pyo3(signature = (workload_path, args=None))]
+    fn run<'py>(
+        &self,
+        py: Python<'py>,
+        workload_path: String,
+        args: Option<Vec<String>>,
+    ) -> PyResult<&'py PyAny> {
+        let sandbox = Arc::clone(&self.sandbox);
+        let args = args.unwrap_or_default();
 
         pyo3_asyncio::tokio::future_into_py(py, async move {
-            match runtime.run(&workload_path).await {
-                Ok(()) => Ok(WorkloadResult {
-                    success: true,
-                    error: None,
-                }),
-                Err(e) => Ok(WorkloadResult {
-                    success: false,
-                    error: Some(format!("Workload execution failed: {}", e)),
-                }),
-            }
+            let output = sandbox
+                .lock()
+                .await
+                .run_with_args(&workload_path, &args)
+                .await
+                .map_err(to_py_err)?;
+            Ok(WorkloadResult::from(output))
+        })
+    }
+
+    /// Run inline source code in the sandbox
+    ///
+    /// Args:
+    ///     workload_type: Either "javascript" or "python"
+    ///     code: Source code to run
+    ///
+    /// Returns:
+    ///     WorkloadResult with the captured output, exit code and duration
+    ///
+    /// Raises:
+    ///     NanvixError: A subclass such as InvalidWorkloadError or
+    ///         WorkloadTimeoutError if the code could not be run
+    ///
+    /// Example:
+    ///     >>> result = await sandbox.run_code("python", "print('hi')")
+    ///     >>> print(result.console)
+    fn run_code<'py>(
+        &self,
+        py: Python<'py>,
+        workload_type: String,
+        code: String,
+    ) -> PyResult<&'py PyAny> {
+        let sandbox = Arc::clone(&self.sandbox);
+        let workload_type: WorkloadType = workload_type.parse().map_err(to_py_err)?;
+
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let output = sandbox
+                .lock()
+                .await
+                .run_source(workload_type, &code)
+                .await
+                .map_err(to_py_err)?;
+            Ok(WorkloadResult::from(output))
+        })
+    }
+
+    /// Cancel the workload currently running in this sandbox
+    ///
+    /// Example:
+    ///     >>> sandbox.cancel()
+    fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Snapshot a booted guest so later runs skip kernel boot and interpreter start-up
+    ///
+    /// Args:
+    ///     workload_type: Either "javascript" or "python"
+    ///
+    /// Returns:
+    ///     Path of the persisted snapshot
+    ///
+    /// Example:
+    ///     >>> path = await sandbox.snapshot("python")
+    fn snapshot<'py>(&self, py: Python<'py>, workload_type: String) -> PyResult<&'py PyAny> {
+        let sandbox = Arc::clone(&self.sandbox);
+        let workload_type: WorkloadType = workload_type.parse().map_err(to_py_err)?;
+
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let path = sandbox
+                .lock()
+                .await
+                .snapshot(workload_type)
+                .await
+                .map_err(to_py_err)?;
+            Ok(path.to_string_lossy().into_owned())
         })
     }
 
@@ -130,13 +403,15 @@ impl NanvixSandbox {
     /// Example:
     ///     >>> success = await sandbox.clear_cache()
     fn clear_cache<'py>(&self, py: Python<'py>) -> PyResult<&'py PyAny> {
-        let runtime = Arc::clone(&self.runtime);
+        let sandbox = Arc::clone(&self.sandbox);
 
         pyo3_asyncio::tokio::future_into_py(py, async move {
-            runtime
+            sandbox
+                .lock()
+                .await
                 .clear_cache()
                 .await
-                .map_err(|e| PyRuntimeError::new_err(format!("Failed to clear cache: {}", e)))?;
+                .map_err(to_py_err)?;
             Ok(true)
         })
     }
@@ -146,11 +421,191 @@ impl NanvixSandbox {
     }
 }
 
+/// Python wrapper for a pool of pre-warmed hyperlight-nanvix sandboxes
+#[pyclass]
+pub struct NanvixSandboxPool {
+    pool: SandboxPool,
+}
+
+#[pymethods]
+impl NanvixSandboxPool {
+    /// Create a new pool, await warm() to boot its guests ahead of the first run
+    ///
+    /// Args:
+    ///     config: Optional SandboxConfig applied to every guest
+    ///     pool_config: Optional PoolConfig with size, max_reuse, idle_timeout
+    ///         and workload_types
+    ///
+    /// Example:
+    ///     >>> pool = NanvixSandboxPool(pool_config=PoolConfig(size=4))
+    ///     >>> await pool.warm()
+    #[new]
+    #[pyo3(signature = (config=None, pool_config=None))]
+    fn new(config: Option<SandboxConfig>, pool_config: Option<PoolConfig>) -> PyResult<Self> {
+        let mut runtime_pool_config = RuntimePoolConfig::new();
+        if let Some(cfg) = pool_config {
+            if let Some(size) = cfg.size {
+                runtime_pool_config = runtime_pool_config.with_size(size);
+            }
+            if let Some(max_reuse) = cfg.max_reuse {
+                runtime_pool_config = runtime_pool_config.with_max_reuse(max_reuse);
+            }
+            if let Some(idle_timeout) = cfg.idle_timeout {
+                let idle_timeout = std::time::Duration::try_from_secs_f64(idle_timeout)
+                    .map_err(|e| PyValueError::new_err(format!("Invalid idle_timeout: {}", e)))?;
+                runtime_pool_config = runtime_pool_config.with_idle_timeout(Some(idle_timeout));
+            }
+            if let Some(workload_types) = cfg.workload_types {
+                let workload_types = workload_types
+                    .iter()
+                    .map(|name| name.parse::<WorkloadType>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(to_py_err)?;
+                runtime_pool_config = runtime_pool_config.with_workload_types(workload_types);
+            }
+        }
+
+        let pool =
+            SandboxPool::new(runtime_config(config)?, runtime_pool_config).map_err(to_py_err)?;
+        Ok(Self { pool })
+    }
+
+    /// Boot guests until every configured workload type has its warm guests
+    ///
+    /// Example:
+    ///     >>> await pool.warm()
+    fn warm<'py>(&self, py: Python<'py>) -> PyResult<&'py PyAny> {
+        let pool = self.pool.clone();
+
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            pool.warm().await.map_err(to_py_err)?;
+            Ok(())
+        })
+    }
+
+    /// Run a workload on a warm guest
+    ///
+    /// Args:
+    ///     workload_path: Path to the workload file (JavaScript, Python, or binary)
+    ///     args: Optional list of command-line arguments passed to the workload
+    ///
+    /// Returns:
+    ///     WorkloadResult with the captured output, exit code and duration
+    ///
+    /// Example:
+    ///     >>> result = await pool.run("script.py")
+    #[pyo3(signature = (workload_path, args=None))]
+    fn run<'py>(
+        &self,
+        py: Python<'py>,
+        workload_path: String,
+        args: Option<Vec<String>>,
+    ) -> PyResult<&'py PyAny> {
+        let pool = self.pool.clone();
+        let args = args.unwrap_or_default();
+
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let output = pool
+                .run_with_args(&workload_path, &args)
+                .await
+                .map_err(to_py_err)?;
+            Ok(WorkloadResult::from(output))
+        })
+    }
+
+    /// Run inline source code on a warm guest
+    ///
+    /// Example:
+    ///     >>> result = await pool.run_code("javascript", "console.log('hi')")
+    fn run_code<'py>(
+        &self,
+        py: Python<'py>,
+        workload_type: String,
+        code: String,
+    ) -> PyResult<&'py PyAny> {
+        let pool = self.pool.clone();
+        let workload_type: WorkloadType = workload_type.parse().map_err(to_py_err)?;
+
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let output = pool
+                .run_source(workload_type, &code)
+                .await
+                .map_err(to_py_err)?;
+            Ok(WorkloadResult::from(output))
+        })
+    }
+
+    /// Cancel the workloads currently running in this pool
+    fn cancel(&self) {
+        self.pool.cancel_handle().cancel();
+    }
+
+    /// Number of idle warm guests
+    #[getter]
+    fn idle_count(&self) -> usize {
+        self.pool.idle_count()
+    }
+
+    /// Tear down guests idle for longer than the idle timeout
+    ///
+    /// Returns:
+    ///     The number of evicted guests
+    fn evict_idle(&self) -> usize {
+        self.pool.evict_idle()
+    }
+
+    /// Tear down every idle guest
+    fn shutdown(&self) {
+        self.pool.shutdown();
+    }
+
+    fn __repr__(&self) -> String {
+        format!("NanvixSandboxPool(idle_count={})", self.pool.idle_count())
+    }
+}
+
 /// Initialize the Python module
 #[pymodule]
-fn hyperlight_nanvix(_py: Python, m: &PyModule) -> PyResult<()> {
+fn hyperlight_nanvix(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<NanvixSandbox>()?;
     m.add_class::<SandboxConfig>()?;
     m.add_class::<WorkloadResult>()?;
+    m.add_class::<NanvixSandboxPool>()?;
+    m.add_class::<PoolConfig>()?;
+    m.add("NanvixError", py.get_type::<NanvixError>())?;
+    m.add(
+        "UnknownWorkloadTypeError",
+        py.get_type::<UnknownWorkloadTypeError>(),
+    )?;
+    m.add(
+        "WorkloadNotFoundError",
+        py.get_type::<WorkloadNotFoundError>(),
+    )?;
+    m.add(
+        "InvalidWorkloadError",
+        py.get_type::<InvalidWorkloadError>(),
+    )?;
+    m.add(
+        "RegistryUnavailableError",
+        py.get_type::<RegistryUnavailableError>(),
+    )?;
+    m.add(
+        "InterpreterMissingError",
+        py.get_type::<InterpreterMissingError>(),
+    )?;
+    m.add("KernelMissingError", py.get_type::<KernelMissingError>())?;
+    m.add("NotCachedError", py.get_type::<NotCachedError>())?;
+    m.add("IntegrityError", py.get_type::<IntegrityError>())?;
+    m.add("GuestCrashedError", py.get_type::<GuestCrashedError>())?;
+    m.add("GuestExitError", py.get_type::<GuestExitError>())?;
+    m.add(
+        "WorkloadTimeoutError",
+        py.get_type::<WorkloadTimeoutError>(),
+    )?;
+    m.add(
+        "WorkloadCancelledError",
+        py.get_type::<WorkloadCancelledError>(),
+    )?;
+    m.add("LimitExceededError", py.get_type::<LimitExceededError>())?;
     Ok(())
 }
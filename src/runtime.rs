@@ -1,15 +1,32 @@
-use anyhow::Result;
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
 
 use nanvix::log;
 use nanvix::registry::Registry;
 use nanvix::sandbox_cache::SandboxCacheConfig;
 use nanvix::terminal::Terminal;
 
-use crate::cache;
+use crate::audit::AuditSink;
+use crate::cache::{Cache, CachedBinary, Flavor, Release, VersionReq};
+use crate::elf::{check_guest_binary, ELF_MAGIC};
+use crate::error::{Error, Result};
+use crate::faults::{Fault, FaultInjector};
+use crate::hooks::{SyscallHandler, SyscallHooks};
+use crate::integrity::DigestStore;
+use crate::limits::{Limit, ResourceLimits, ResourceMonitor};
+use crate::lockfile::{LockedPackage, Lockfile};
+use crate::policy::SyscallPolicy;
+use crate::snapshot::{SnapshotKey, SnapshotStore, StagedSnapshot};
+use crate::syscalls::{check_embedder_table, GuestSyscalls, RunState};
+use crate::trace::{SyscallTrace, TraceHeader, TraceReplay, TraceWriter};
 
 /// Supported workload types
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum WorkloadType {
     JavaScript,
     Python,
@@ -41,43 +58,220 @@ impl WorkloadType {
         match self {
             WorkloadType::JavaScript => &["js", "mjs"],
             WorkloadType::Python => &["py"],
-            WorkloadType::Binary => &["elf", "o"],
+            WorkloadType::Binary => &["elf"],
         }
     }
 
-    /// Detect workload type from file extension
+    /// Detect workload type from file content, falling back to the extension.
+    ///
+    /// ELF files are binaries and scripts with a `#!` line naming `qjs` or
+    /// `python3` run with that interpreter, whatever their extension. Other
+    /// files, and paths that cannot be read, are detected by their extension.
     pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
         let path_ref = path.as_ref();
 
-        if let Some(extension) = path_ref.extension() {
-            let ext_str = extension.to_str()?.to_lowercase();
-            match ext_str.as_str() {
-                "js" | "mjs" => Some(WorkloadType::JavaScript),
-                "py" => Some(WorkloadType::Python),
-                "elf" | "o" => Some(WorkloadType::Binary),
-                _ => None,
-            }
-        } else {
-            // Check if it's an executable binary without extension
-            if path_ref.is_file() {
-                // Simple heuristic: if it has no extension and is a file, treat as binary
-                Some(WorkloadType::Binary)
-            } else {
-                None
-            }
+        let mut head = [0; 256];
+        let read = std::fs::File::open(path_ref)
+            .and_then(|file| std::os::unix::fs::FileExt::read_at(&file, &mut head, 0))
+            .unwrap_or(0);
+        let head = &head[..read];
+        if head.starts_with(ELF_MAGIC) {
+            return Some(WorkloadType::Binary);
+        }
+        if let Some(workload_type) = Self::from_shebang(head) {
+            return Some(workload_type);
+        }
+
+        let extension = path_ref.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "js" | "mjs" => Some(WorkloadType::JavaScript),
+            "py" => Some(WorkloadType::Python),
+            // A file that is not an ELF is not a binary, whatever its name
+            _ => None,
+        }
+    }
+
+    /// Detect a script from its `#!` line, e.g. `#!/usr/bin/env python3`
+    fn from_shebang(head: &[u8]) -> Option<Self> {
+        let line = head.strip_prefix(b"#!")?.split(|&b| b == b'\n').next()?;
+        let mut words = std::str::from_utf8(line).ok()?.split_whitespace();
+        let mut interpreter = words.next()?;
+        if interpreter.rsplit('/').next() == Some("env") {
+            // Skip options such as `-S` and variable assignments
+            interpreter = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+        }
+        match interpreter.rsplit('/').next()? {
+            "qjs" | "quickjs" => Some(WorkloadType::JavaScript),
+            name if name == "python" || name.starts_with("python3") => Some(WorkloadType::Python),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for WorkloadType {
+    type Err = Error;
+
+    /// Parse a workload type name such as `"javascript"`, `"js"`, `"python"` or `"py"`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "javascript" | "js" => Ok(WorkloadType::JavaScript),
+            "python" | "py" => Ok(WorkloadType::Python),
+            "binary" => Ok(WorkloadType::Binary),
+            _ => Err(Error::InvalidWorkload(format!(
+                "Unknown workload type: {}",
+                name
+            ))),
         }
     }
 }
 
+/// Exit status of a guest process, as far as nanvix reports it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExitStatus(Option<i32>);
+
+impl ExitStatus {
+    /// Create an exit status from a raw guest exit code
+    pub fn from_code(code: i32) -> Self {
+        Self(Some(code))
+    }
+
+    /// Exit status of a run that nanvix completed without reporting an exit code
+    pub fn unknown() -> Self {
+        Self(None)
+    }
+
+    /// Get the raw exit code reported by the guest, `None` if nanvix did not report one
+    pub fn code(&self) -> Option<i32> {
+        self.0
+    }
+
+    /// Returns `true` unless the guest reported a non-zero exit code.
+    ///
+    /// A run without a reported exit code completed without an error from
+    /// nanvix, so it counts as successful.
+    pub fn success(&self) -> bool {
+        self.0.unwrap_or(0) == 0
+    }
+}
+
+impl std::fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(code) => write!(f, "exit status: {}", code),
+            None => write!(f, "exit status: unknown"),
+        }
+    }
+}
+
+/// Output captured from a single workload run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunOutput {
+    /// Everything the guest wrote to its console.
+    ///
+    /// The Nanvix console carries both guest streams, so anything written to
+    /// stderr inside the guest is interleaved here.
+    pub console: String,
+    /// Exit status reported by nanvix for the guest process
+    pub exit_status: ExitStatus,
+    /// Wall-clock time spent booting and running the guest
+    pub duration: Duration,
+    /// Trace of the run's syscalls, when recording is enabled
+    pub syscall_trace: Option<PathBuf>,
+}
+
+impl RunOutput {
+    /// Returns `true` if the guest exited with status zero
+    pub fn success(&self) -> bool {
+        self.exit_status.success()
+    }
+
+    /// Turn a non-zero guest exit status into [`Error::GuestExit`]
+    pub fn exit_ok(self) -> Result<Self> {
+        match self.exit_status.code() {
+            Some(code) if code != 0 => Err(Error::GuestExit(code)),
+            _ => Ok(self),
+        }
+    }
+}
+
+/// Handle for cancelling the workloads running in a [`Runtime`] from another task
+#[derive(Debug, Clone)]
+pub struct CancelHandle {
+    cancel: Arc<watch::Sender<u64>>,
+}
+
+impl CancelHandle {
+    /// Cancel the runs in progress.
+    ///
+    /// Every cancelled run tears down its guest and fails with [`Error::Cancelled`].
+    /// Runs started afterwards are not affected, so cancelling when no run is in
+    /// progress does nothing.
+    pub fn cancel(&self) {
+        // Each run waits for the generation to move past the one it started in
+        self.cancel.send_modify(|generation| *generation += 1);
+    }
+}
+
+/// Registry package providing the Nanvix kernel
+const KERNEL_PACKAGE: &str = "kernel";
+
+/// File name of the Nanvix kernel binary
+pub(crate) const KERNEL_BINARY: &str = "kernel.elf";
+
+/// Environment variable that enables offline mode when set to `1`, `true`, `yes` or `on`
+pub const OFFLINE_ENV_VAR: &str = "HYPERLIGHT_NANVIX_OFFLINE";
+
+/// Interpret the value of a boolean environment variable
+pub fn env_flag_enabled(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
 /// Runtime configuration for hyperlight-nanvix
 #[derive(Clone)]
 pub struct RuntimeConfig {
-    /// Optional custom syscall table
+    /// Optional custom syscall table; its entries must be `Forward` handlers or
+    /// left at their default so the runtime can chain to them
     pub syscall_table: Option<std::sync::Arc<nanvix::sandbox::SyscallTable<()>>>,
+    /// Safe syscall hooks, called before the handlers of the syscall table
+    pub syscall_hooks: Option<Arc<dyn SyscallHandler>>,
+    /// Policy deciding which guest syscalls are performed
+    pub syscall_policy: Option<SyscallPolicy>,
+    /// Sink receiving an event for every intercepted guest syscall, only
+    /// `openat` with this nanvix release
+    pub syscall_audit: Option<Arc<dyn AuditSink>>,
+    /// Record the syscalls of every run to a trace file in the log directory
+    pub record_syscalls: bool,
+    /// Trace whose recorded syscall outcomes are fed back to every run
+    pub syscall_replay: Option<Arc<SyscallTrace>>,
+    /// Faults injected into guest syscalls, the first one that fires wins
+    pub faults: Vec<Fault>,
+    /// Seed of the probabilistic faults, so runs are reproducible
+    pub fault_seed: u64,
     /// Directory for storing logs
     pub log_directory: String,
     /// Directory for temporary files
     pub tmp_directory: String,
+    /// Maximum wall-clock time a single run may take
+    pub timeout: Option<Duration>,
+    /// Never contact the network registry, fail if a binary is not cached
+    pub offline: bool,
+    /// Root of the nanvix-registry cache, `None` for `~/.cache/nanvix-registry`
+    pub cache_directory: Option<String>,
+    /// Registry machine/deployment flavor, hyperlight single-process by default
+    pub flavor: Flavor,
+    /// Version requirement for the registry release every binary is taken from, e.g. `"0.4.x"`
+    pub release_version: Option<String>,
+    /// Lockfile recording the exact release and hash of every binary used
+    pub lockfile: Option<String>,
+    /// File holding the recorded digests of cached binaries, `None` for the per-user default
+    pub digest_file: Option<String>,
+    /// Directory of persisted guest snapshots, `None` for `~/.cache/hyperlight-nanvix/snapshots`
+    pub snapshot_directory: Option<String>,
+    /// Memory, CPU-time and output limits applied to every run
+    pub limits: ResourceLimits,
 }
 
 impl std::fmt::Debug for RuntimeConfig {
@@ -87,8 +281,36 @@ impl std::fmt::Debug for RuntimeConfig {
                 "syscall_table",
                 &self.syscall_table.as_ref().map(|_| "SyscallTable<()>"),
             )
+            .field(
+                "syscall_hooks",
+                &self.syscall_hooks.as_ref().map(|_| "SyscallHandler"),
+            )
+            .field("syscall_policy", &self.syscall_policy)
+            .field(
+                "syscall_audit",
+                &self.syscall_audit.as_ref().map(|_| "AuditSink"),
+            )
+            .field("record_syscalls", &self.record_syscalls)
+            .field(
+                "syscall_replay",
+                &self
+                    .syscall_replay
+                    .as_ref()
+                    .map(|trace| &trace.header.workload_path),
+            )
+            .field("faults", &self.faults)
+            .field("fault_seed", &self.fault_seed)
             .field("log_directory", &self.log_directory)
             .field("tmp_directory", &self.tmp_directory)
+            .field("timeout", &self.timeout)
+            .field("offline", &self.offline)
+            .field("cache_directory", &self.cache_directory)
+            .field("flavor", &self.flavor)
+            .field("release_version", &self.release_version)
+            .field("lockfile", &self.lockfile)
+            .field("digest_file", &self.digest_file)
+            .field("snapshot_directory", &self.snapshot_directory)
+            .field("limits", &self.limits)
             .finish()
     }
 }
@@ -109,8 +331,24 @@ impl Default for RuntimeConfig {
 
         Self {
             syscall_table: None,
+            syscall_hooks: None,
+            syscall_policy: None,
+            syscall_audit: None,
+            record_syscalls: false,
+            syscall_replay: None,
+            faults: Vec::new(),
+            fault_seed: 0,
             log_directory: format!("/tmp/hyperlight-nanvix-{}", unique_suffix),
             tmp_directory: format!("/tmp/hyperlight-nanvix-{}", unique_suffix),
+            timeout: None,
+            offline: std::env::var(OFFLINE_ENV_VAR).is_ok_and(|value| env_flag_enabled(&value)),
+            cache_directory: None,
+            flavor: Flavor::default(),
+            release_version: None,
+            lockfile: None,
+            digest_file: None,
+            snapshot_directory: None,
+            limits: ResourceLimits::default(),
         }
     }
 }
@@ -128,6 +366,85 @@ impl RuntimeConfig {
         self
     }
 
+    /// Handle guest syscalls with safe closures sharing typed state.
+    ///
+    /// Hooks run after the policy, and before the handlers of the
+    /// syscall table.
+    pub fn with_syscall_hooks<S: Send + Sync + 'static>(mut self, hooks: SyscallHooks<S>) -> Self {
+        self.syscall_hooks = Some(Arc::new(hooks));
+        self
+    }
+
+    /// Restrict guest syscalls with a declarative policy.
+    ///
+    /// The policy is checked before the handlers of the syscall table. Its
+    /// rules are validated when the runtime is created, so a rule for a
+    /// syscall that cannot be intercepted fails with [`Error::InvalidPolicy`].
+    pub fn with_syscall_policy(mut self, policy: SyscallPolicy) -> Self {
+        self.syscall_policy = Some(policy);
+        self
+    }
+
+    /// Record every guest syscall the host intercepts, with its decoded
+    /// arguments and outcome, in an audit sink.
+    ///
+    /// Every entry of the nanvix syscall table is wrapped, but the table of
+    /// this nanvix release has a single entry, `openat`, so only opens are
+    /// audited. Reads, writes, `unlink` and the other syscalls of the guest
+    /// never reach the sink.
+    ///
+    /// Use [`JsonLinesSink`](crate::audit::JsonLinesSink) to write the events
+    /// as JSON lines, or pass a closure to consume them directly.
+    pub fn with_syscall_audit<S: AuditSink + 'static>(mut self, sink: S) -> Self {
+        self.syscall_audit = Some(Arc::new(sink));
+        self
+    }
+
+    /// Record the syscalls of every run, and the files they opened, to a
+    /// trace file in the log directory.
+    ///
+    /// Only the syscalls the host intercepts are recorded, which is `openat`
+    /// alone with this nanvix release. Clock reads, reads and writes of open
+    /// files and the other syscalls are not, so a replay is not bit-for-bit.
+    ///
+    /// The path of the trace is returned in [`RunOutput::syscall_trace`].
+    pub fn with_syscall_recording(mut self, record: bool) -> Self {
+        self.record_syscalls = record;
+        self
+    }
+
+    /// Replay a recorded trace: every run gets the recorded syscall outcomes
+    /// and file contents instead of touching the host.
+    ///
+    /// Like recording, replay only covers `openat` with this nanvix release.
+    ///
+    /// A run that makes different syscalls than were recorded fails with
+    /// [`Error::ReplayDiverged`].
+    pub fn with_syscall_replay(mut self, trace: SyscallTrace) -> Self {
+        self.syscall_replay = Some(Arc::new(trace));
+        self
+    }
+
+    /// Make a guest syscall fail with an error number, to test how the guest
+    /// copes with I/O errors.
+    ///
+    /// Faults are checked in the order they were added. Only the syscalls in
+    /// [`INTERCEPTABLE_SYSCALLS`](crate::policy::INTERCEPTABLE_SYSCALLS) can
+    /// fail, which is `openat` alone with this nanvix release. Faults on
+    /// `read`, `write` and other syscalls are rejected with
+    /// [`Error::InvalidFault`] when the runtime is created.
+    pub fn with_fault(mut self, fault: Fault) -> Self {
+        self.faults.push(fault);
+        self
+    }
+
+    /// Seed the probabilistic faults; every run starts from the seed, so a
+    /// run making the same syscalls sees the same faults
+    pub fn with_fault_seed(mut self, seed: u64) -> Self {
+        self.fault_seed = seed;
+        self
+    }
+
     pub fn with_log_directory<S: Into<String>>(mut self, dir: S) -> Self {
         self.log_directory = dir.into();
         self
@@ -137,134 +454,427 @@ impl RuntimeConfig {
         self.tmp_directory = dir.into();
         self
     }
+
+    /// Bound how long a single run may take before the guest is torn down,
+    /// including the wait for its turn when process limits are set
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Only use binaries that are already cached and never download from the registry.
+    ///
+    /// Defaults to the value of the `HYPERLIGHT_NANVIX_OFFLINE` environment variable.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Use a specific nanvix-registry cache directory for lookups and downloads
+    pub fn with_cache_directory<S: Into<String>>(mut self, dir: S) -> Self {
+        self.cache_directory = Some(dir.into());
+        self
+    }
+
+    /// Select the registry machine/deployment flavor used for downloads and cache lookups
+    pub fn with_flavor(mut self, flavor: Flavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Pin the registry release to a version requirement such as `"0.4.x"`.
+    ///
+    /// A registry release ships the kernel and every interpreter together, so
+    /// the requirement is matched against the release version (the suffix of
+    /// its `<machine>-<deployment>-<version>` cache directory) and the kernel
+    /// and interpreter of a run always come from the same release. It is
+    /// validated when the runtime is created.
+    pub fn with_release_version<V: Into<String>>(mut self, version: V) -> Self {
+        self.release_version = Some(version.into());
+        self
+    }
+
+    /// Take binaries from the exact releases recorded in a lockfile, verifying their hashes.
+    ///
+    /// Binaries missing from the lockfile are resolved normally and recorded.
+    pub fn with_lockfile<S: Into<String>>(mut self, path: S) -> Self {
+        self.lockfile = Some(path.into());
+        self
+    }
+
+    /// Record and verify the digests of cached binaries in a specific file
+    pub fn with_digest_file<S: Into<String>>(mut self, path: S) -> Self {
+        self.digest_file = Some(path.into());
+        self
+    }
+
+    /// Persist guest snapshots taken with `Sandbox::snapshot` in a specific directory
+    pub fn with_snapshot_directory<S: Into<String>>(mut self, dir: S) -> Self {
+        self.snapshot_directory = Some(dir.into());
+        self
+    }
+
+    /// Tear a run down with [`Error::LimitExceeded`] once the host process
+    /// allocates more than this many bytes of memory while it executes.
+    ///
+    /// This is a watchdog on the whole process, not a quota on the guest; see
+    /// [`ResourceLimits`]. Runs with a process limit execute one at a time.
+    pub fn with_process_memory_limit(mut self, bytes: u64) -> Self {
+        self.limits.process_memory = Some(bytes);
+        self
+    }
+
+    /// Tear a run down with [`Error::LimitExceeded`] once the host process
+    /// consumes more CPU time than this while it executes.
+    ///
+    /// This is a watchdog on the whole process, not a quota on the guest; see
+    /// [`ResourceLimits`]. Runs with a process limit execute one at a time.
+    pub fn with_process_cpu_time_limit(mut self, budget: Duration) -> Self {
+        self.limits.process_cpu_time = Some(budget);
+        self
+    }
+
+    /// Tear a run down with [`Error::LimitExceeded`] once it writes more than this
+    /// many bytes to its console
+    pub fn with_output_limit(mut self, bytes: u64) -> Self {
+        self.limits.output = Some(bytes);
+        self
+    }
 }
 
 /// Runtime for executing workloads in Nanvix sandboxes
 pub struct Runtime {
     config: RuntimeConfig,
+    cache: Cache,
     registry: Registry,
+    release_version: Option<VersionReq>,
+    /// Workload types whose packages were installed by this runtime, so an
+    /// unsatisfiable pin does not reinstall on every run
+    installed: Mutex<HashSet<WorkloadType>>,
+    lockfile: Option<Mutex<Lockfile>>,
+    snapshots: SnapshotStore,
+    faults: Option<FaultInjector>,
+    cancel: Arc<watch::Sender<u64>>,
+    /// Number of guests prepared for runs, to tell a reused guest from a fresh one
+    prepared: AtomicUsize,
+    /// Number of guests restored from a persisted snapshot
+    restored: AtomicUsize,
+    /// Held by runs with a memory or CPU-time limit, whose usage is measured
+    /// for the whole host process
+    measured_run: tokio::sync::Mutex<()>,
 }
 
 impl Runtime {
     pub fn new(config: RuntimeConfig) -> Result<Self> {
-        let registry = Registry::new(None);
-        Ok(Self { config, registry })
+        let mut cache = match &config.cache_directory {
+            Some(dir) => Cache::new(dir),
+            None => Cache::default(),
+        }
+        .with_flavor(config.flavor.clone());
+        if let Some(digest_file) = &config.digest_file {
+            cache = cache.with_digest_store(DigestStore::new(digest_file));
+        }
+        let mut snapshots = match &config.snapshot_directory {
+            Some(dir) => SnapshotStore::new(dir),
+            None => SnapshotStore::default(),
+        };
+        snapshots = snapshots.with_digest_store(cache.digest_store().clone());
+        let registry = cache.registry();
+        let release_version = config
+            .release_version
+            .as_deref()
+            .map(str::parse)
+            .transpose()?;
+        let lockfile = config
+            .lockfile
+            .as_ref()
+            .map(|path| Lockfile::open(path).map(Mutex::new))
+            .transpose()?;
+        // Rules may have been pushed to the public fields without validation
+        if let Some(policy) = &config.syscall_policy {
+            policy.validate()?;
+        }
+        if let Some(table) = &config.syscall_table {
+            check_embedder_table(table)?;
+        }
+        let faults = (!config.faults.is_empty())
+            .then(|| FaultInjector::new(&config.faults, config.fault_seed))
+            .transpose()?;
+        let (cancel, _) = watch::channel(0);
+        Ok(Self {
+            config,
+            cache,
+            registry,
+            release_version,
+            installed: Mutex::new(HashSet::new()),
+            lockfile,
+            snapshots,
+            faults,
+            cancel: Arc::new(cancel),
+            prepared: AtomicUsize::new(0),
+            restored: AtomicUsize::new(0),
+            measured_run: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Get a handle that can cancel runs of this runtime from another task
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            cancel: Arc::clone(&self.cancel),
+        }
     }
 
     /// Clear the nanvix registry cache to force fresh downloads
     pub async fn clear_cache(&self) -> Result<()> {
         log::info!("Clearing nanvix registry cache...");
         self.registry.clear_cache().await?;
+        self.cache.digest_store().forget_under(self.cache.root())?;
         log::info!("Cache cleared successfully");
         Ok(())
     }
 
-    /// Run a workload
-    pub async fn run<P: AsRef<Path>>(&self, workload_path: P) -> Result<()> {
+    /// Remove every persisted guest snapshot, returning how many were removed
+    pub fn clear_snapshots(&self) -> Result<usize> {
+        self.snapshots.clear()
+    }
+
+    /// Take a snapshot of a guest whose kernel has booted and whose interpreter
+    /// has initialised, and persist it for later runs.
+    ///
+    /// Later guests of the same workload type are restored from the snapshot
+    /// instead of booting, as long as the kernel and interpreter binaries are
+    /// unchanged. Returns the path of the snapshot.
+    ///
+    /// The pinned nanvix revision has no call that snapshots a running guest.
+    /// A guest started with `use_snapshot` set restores the file at
+    /// `snapshot_path` if there is one, and otherwise writes its state there
+    /// once it has initialised. The guest is therefore handed a freshly staged
+    /// path that cannot exist yet, and the snapshot is only committed if the
+    /// guest actually wrote it.
+    pub async fn snapshot(&self, workload_type: WorkloadType) -> Result<PathBuf> {
+        if workload_type == WorkloadType::Binary {
+            return Err(Error::InvalidWorkload(
+                "Snapshots are only supported for JavaScript and Python workloads".to_string(),
+            ));
+        }
+
+        let (mut guest, staged) = self.start_guest(workload_type, true).await?;
+        let (Some(key), Some(staged)) = (guest.snapshot_key.clone(), staged) else {
+            return Err(Error::SnapshotFailed(format!(
+                "no snapshot key for {} workloads",
+                workload_type.binary_name()
+            )));
+        };
+
+        // An empty warm-up run initialises the interpreter before the snapshot is taken
+        let warm_up = self.stage_source(workload_type, "")?;
+        self.execute(&mut guest, &warm_up.0, &[] as &[&str])
+            .await?
+            .exit_ok()?;
+
+        // Tear the guest down so that the snapshot is completely written
+        drop(guest);
+        let path = self.snapshots.commit(&key, staged)?;
+        log::info!("Saved snapshot {}", path.display());
+        Ok(path)
+    }
+
+    /// Run a workload and capture its output.
+    ///
+    /// A guest that exits with a non-zero status still produces a [`RunOutput`];
+    /// use [`RunOutput::exit_ok`] to turn that into [`Error::GuestExit`].
+    pub async fn run<P: AsRef<Path>>(&self, workload_path: P) -> Result<RunOutput> {
+        self.run_with_args(workload_path, &[] as &[&str]).await
+    }
+
+    /// Run a workload with additional command-line arguments and capture its output.
+    ///
+    /// The arguments are appended after the script path, so they show up in
+    /// `scriptArgs` (JavaScript), `sys.argv` (Python) or `argv` (binaries).
+    pub async fn run_with_args<P: AsRef<Path>, S: AsRef<str>>(
+        &self,
+        workload_path: P,
+        args: &[S],
+    ) -> Result<RunOutput> {
         let workload_path = workload_path.as_ref();
+        let workload_type = Self::resolve_workload(workload_path)?;
+        let mut guest = self.prepare_guest(workload_type).await?;
+        self.execute(&mut guest, workload_path, args).await
+    }
+
+    /// Run inline source code and capture its output.
+    ///
+    /// The source is staged in a uniquely named file under
+    /// `{tmp_directory}/sources`, which is removed once the run ends.
+    pub async fn run_source(&self, workload_type: WorkloadType, source: &str) -> Result<RunOutput> {
+        let source_file = self.stage_source(workload_type, source)?;
+        self.run(&source_file.0).await
+    }
 
-        // Determine workload type from file extension
-        let workload_type = WorkloadType::from_path(workload_path).ok_or_else(|| {
-            anyhow::anyhow!("Could not determine workload type for {:?}", workload_path)
-        })?;
+    /// Determine the type of a workload and check that it exists
+    pub(crate) fn resolve_workload(workload_path: &Path) -> Result<WorkloadType> {
+        // Determine workload type from file content or extension
+        let workload_type = WorkloadType::from_path(workload_path)
+            .ok_or_else(|| Error::UnknownWorkloadType(workload_path.to_path_buf()))?;
 
         // Verify the workload file exists before proceeding
         if !workload_path.exists() {
-            anyhow::bail!("Workload file not found: {:?}", workload_path);
-        }
-
-        // Use hardcoded values for machine and deployment type (hyperlight single-process)
-        let machine_type = "hyperlight";
-        let deployment_type = "single-process";
-
-        // Install the required package (and its dependencies) for scripted workloads,
-        // but only when the interpreter binary is not already present in the cache.
-        // This avoids unnecessary I/O and network calls on the common (cached) path.
-        if let Some(package_name) = workload_type.package_name() {
-            if !cache::is_binary_cached(workload_type.binary_name()) {
-                log::info!("Installing package '{}' and dependencies...", package_name);
-                self.registry
-                    .install(machine_type, deployment_type, package_name, true)
-                    .await?;
-            }
+            return Err(Error::WorkloadNotFound(workload_path.to_path_buf()));
         }
 
-        // Get interpreter binary (only needed for scripted workloads)
-        let binary_path = if matches!(workload_type, WorkloadType::Binary) {
-            // For binary workloads, we don't need an interpreter
-            String::new()
-        } else {
-            cache::get_cached_binary_path(workload_type.binary_name())
-                .await
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Failed to locate {} binary in cache or registry",
-                        workload_type.binary_name()
-                    )
-                })?
+        // A binary built for another machine would only crash the guest
+        if workload_type == WorkloadType::Binary {
+            check_guest_binary(workload_path)?;
+        }
+        Ok(workload_type)
+    }
+
+    /// Write inline source to a uniquely named file under `{tmp_directory}/sources`
+    pub(crate) fn stage_source(
+        &self,
+        workload_type: WorkloadType,
+        source: &str,
+    ) -> Result<TempSource> {
+        static NEXT_SOURCE_ID: AtomicU64 = AtomicU64::new(0);
+
+        let extension = match workload_type {
+            WorkloadType::Binary => {
+                return Err(Error::InvalidWorkload(
+                    "Inline source is only supported for JavaScript and Python workloads"
+                        .to_string(),
+                ))
+            }
+            _ => workload_type.extensions()[0],
         };
 
-        // Get kernel path for terminal configuration
-        let kernel_path = cache::get_cached_binary_path("kernel.elf")
-            .await
-            .ok_or_else(|| anyhow::anyhow!("Failed to locate kernel.elf in cache or registry"))?;
+        let sources_directory = Path::new(&self.config.tmp_directory).join("sources");
+        std::fs::create_dir_all(&sources_directory)?;
 
-        // Ensure the temporary directory exists for socket creation
-        std::fs::create_dir_all(&self.config.tmp_directory)?;
-        std::fs::create_dir_all(&self.config.log_directory)?;
+        let unique_id = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let source_id = NEXT_SOURCE_ID.fetch_add(1, Ordering::Relaxed);
+        let source_file = TempSource(
+            sources_directory.join(format!("source-{}-{}.{}", unique_id, source_id, extension)),
+        );
+        std::fs::write(&source_file.0, source)?;
+        Ok(source_file)
+    }
 
-        // Use syscall table provided by embedder, or create default one
-        let syscall_table = self.config.syscall_table.clone().or_else(|| {
-            use nanvix::sandbox::SyscallTable;
-            Some(std::sync::Arc::new(SyscallTable::new(())))
+    /// Resolve the binaries for a workload type and create a guest terminal.
+    ///
+    /// The guest is restored from a persisted snapshot taken with the same
+    /// binaries when there is one. It can run several workloads of the same
+    /// type one after another.
+    pub(crate) async fn prepare_guest(&self, workload_type: WorkloadType) -> Result<Guest> {
+        let (guest, _) = self.start_guest(workload_type, false).await?;
+        self.prepared.fetch_add(1, Ordering::Relaxed);
+        Ok(guest)
+    }
+
+    /// Number of guests prepared for runs so far, each of which boots a
+    /// kernel or restores a snapshot
+    pub(crate) fn guests_prepared(&self) -> usize {
+        self.prepared.load(Ordering::Relaxed)
+    }
+
+    /// Number of guests restored from a persisted snapshot instead of booting
+    pub(crate) fn snapshots_restored(&self) -> usize {
+        self.restored.load(Ordering::Relaxed)
+    }
+
+    /// Create a guest terminal, either restoring a matching snapshot or, when
+    /// `take_snapshot` is set, letting the guest write a new one
+    async fn start_guest(
+        &self,
+        workload_type: WorkloadType,
+        take_snapshot: bool,
+    ) -> Result<(Guest, Option<StagedSnapshot>)> {
+        static NEXT_GUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+        let flavor = self.cache.flavor();
+        flavor.check_supported()?;
+
+        // The kernel and the interpreter (only needed for scripted workloads)
+        // come from the same registry release
+        let (kernel, interpreter) = self.locate_binaries(workload_type).await?;
+        let kernel_path = kernel.path;
+
+        // Snapshots hold an initialised interpreter, so binary workloads always boot
+        let snapshot_key = interpreter.as_ref().map(|interpreter| {
+            SnapshotKey::new(
+                workload_type,
+                flavor.clone(),
+                &kernel.sha256,
+                &interpreter.sha256,
+            )
         });
+        let binary_path = interpreter
+            .map(|interpreter| interpreter.path)
+            .unwrap_or_default();
 
-        // Convert workload path to absolute path before potentially changing directory
-        let absolute_workload_path = workload_path
-            .canonicalize()
-            .unwrap_or_else(|_| {
-                std::env::current_dir()
-                    .unwrap_or_default()
-                    .join(workload_path)
-            })
-            .to_string_lossy()
-            .to_string();
+        let app_name = format!(
+            "{}-{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+            NEXT_GUEST_ID.fetch_add(1, Ordering::Relaxed)
+        );
 
-        // For Python workloads, change to the registry directory
-        let original_dir = if matches!(workload_type, WorkloadType::Python) {
-            let current_dir = std::env::current_dir().ok();
-            let registry_base = std::path::Path::new(&binary_path)
-                .parent()
-                .and_then(|p| p.parent());
-
-            if let Some(base_path) = registry_base {
-                if let Err(e) = std::env::set_current_dir(base_path) {
-                    log::warn!(
-                        "Failed to change directory to {}: {}",
-                        base_path.display(),
-                        e
-                    );
-                } else {
-                    log::info!("Changed working directory to: {}", base_path.display());
-                }
-            } else {
-                log::warn!(
-                    "Could not determine registry base directory from binary path: {}",
-                    binary_path
-                );
-            }
-            current_dir
-        } else {
-            None
-        };
+        // Each guest creates its sockets in its own directory, so cleaning up
+        // after one guest never touches the sockets of another
+        let guest_directory = format!("{}/guest-{}", &self.config.tmp_directory, app_name);
+        std::fs::create_dir_all(&guest_directory)?;
+        std::fs::create_dir_all(&self.config.log_directory)?;
 
-        // Configure sandbox cache
-        let console_log_path = format!("{}/guest-console.log", &self.config.log_directory);
-        let console_file = Some(console_log_path.clone());
+        // Removes the guest directory once the guest is torn down, even if a
+        // run is interrupted or its future is dropped
+        let cleanup = RunCleanup::new(&guest_directory);
+
+        // Wrap the syscall table provided by embedder so the policy is enforced first
+        // and every syscall is audited, recorded, replayed or injected with faults
+        let run = RunState::default();
+        let syscall_table = GuestSyscalls::new(
+            self.config.syscall_table.clone(),
+            self.config.syscall_policy.as_ref(),
+            &interpreter_files(&binary_path),
+            run.clone(),
+        )
+        .with_run_interception(
+            self.config.record_syscalls
+                || self.config.syscall_replay.is_some()
+                || self.faults.is_some(),
+        )
+        .with_hooks(self.config.syscall_hooks.clone())
+        .with_audit(self.config.syscall_audit.clone())
+        .into_table();
+
+        // Configure sandbox cache. The guest console is written to a per-guest file
+        // so that the output of each run can be returned to the caller.
+        let console_path = format!("{}/console.log", guest_directory);
+        let console_file = Some(console_path.clone());
 
-        // Use tmp_directory for toolchain and snapshot paths to ensure uniqueness
+        // Use tmp_directory for toolchain and snapshot paths to ensure uniqueness,
+        // unless a persisted snapshot is restored or a new one is taken
         let toolchain_path = format!("{}/toolchain", &self.config.tmp_directory);
-        let snapshot_path = format!("{}/snapshot.bin", &self.config.tmp_directory);
+        let mut snapshot_path = format!("{}/snapshot.bin", &self.config.tmp_directory);
+        let mut use_snapshot = false;
+        let mut staged = None;
+        if let Some(key) = &snapshot_key {
+            if take_snapshot {
+                let staged_snapshot = self.snapshots.stage(key)?;
+                snapshot_path = staged_snapshot.path().to_string_lossy().into_owned();
+                use_snapshot = true;
+                staged = Some(staged_snapshot);
+            } else if let Some(path) = self.snapshots.find(key)? {
+                log::info!("Restoring guest from snapshot {}", path.display());
+                snapshot_path = path.to_string_lossy().into_owned();
+                use_snapshot = true;
+                self.restored.fetch_add(1, Ordering::Relaxed);
+            }
+        }
 
         let sandbox_cache_config = SandboxCacheConfig::new(
             nanvix::syscomm::SocketType::Unix,
@@ -275,89 +885,518 @@ impl Runtime {
             None,
             0,
             &kernel_path,
-            syscall_table,
+            Some(syscall_table),
             &toolchain_path,
             &self.config.log_directory,
-            false,
+            use_snapshot,
             &snapshot_path,
-            &self.config.tmp_directory,
+            &guest_directory,
         );
 
-        // Create terminal
-        let mut terminal: Terminal<()> = Terminal::new(sandbox_cache_config);
+        let guest = Guest {
+            terminal: Terminal::new(sandbox_cache_config),
+            _cleanup: cleanup,
+            workload_type,
+            binary_path,
+            snapshot_key,
+            run,
+            app_name,
+            console_path,
+            console_offset: 0,
+            runs: 0,
+            idle_since: Instant::now(),
+        };
+        Ok((guest, staged))
+    }
+
+    /// Run a workload on a prepared guest and capture its output.
+    ///
+    /// A guest whose run failed must not be reused, drop it instead.
+    pub(crate) async fn execute<S: AsRef<str>>(
+        &self,
+        guest: &mut Guest,
+        workload_path: &Path,
+        args: &[S],
+    ) -> Result<RunOutput> {
+        // Convert workload path to absolute path, the guest resolves paths on the host
+        let absolute_workload_path = workload_path
+            .canonicalize()
+            .unwrap_or_else(|_| {
+                std::env::current_dir()
+                    .unwrap_or_default()
+                    .join(workload_path)
+            })
+            .to_string_lossy()
+            .to_string();
 
         // Prepare execution paths and metadata
-        let (script_args, script_name) =
-            self.prepare_script_args(workload_type, Path::new(&absolute_workload_path))?;
-        let effective_binary_path = match workload_type {
-            WorkloadType::Python => "bin/python3".to_string(),
+        let (effective_script_args, script_name) = self.prepare_script_args(
+            guest.workload_type,
+            Path::new(&absolute_workload_path),
+            args,
+        )?;
+        // Interpreters are started from their absolute cache path. Python derives
+        // its prefix (and thus the stdlib location) from the executable path, so
+        // this works without changing the process-wide working directory.
+        let effective_binary_path = match guest.workload_type {
             WorkloadType::Binary => absolute_workload_path.clone(),
-            _ => binary_path.clone(),
-        };
-        let effective_script_args = match workload_type {
-            WorkloadType::Binary => String::new(), // No args for binary execution
-            _ => script_args,
+            _ => guest.binary_path.clone(),
         };
 
-        let unique_app_name = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_nanos()
-            .to_string();
-
         log::info!(
             "Running {} workload: {:?}",
-            workload_type.binary_name(),
+            guest.workload_type.binary_name(),
             workload_path
         );
         log::debug!("Binary path: {}", effective_binary_path);
         log::debug!("Script args: {}", effective_script_args);
 
-        // Execute workload
-        terminal
-            .run(
-                Some(&script_name),
-                Some(&unique_app_name),
-                &effective_binary_path,
-                &effective_script_args,
-            )
-            .await?;
+        // Record the run from the start, so the trace holds the workload as it was run
+        let recorder = if self.config.record_syscalls {
+            let header = TraceHeader {
+                workload_path: PathBuf::from(&absolute_workload_path),
+                args: args.iter().map(|arg| arg.as_ref().to_string()).collect(),
+                recorded_at_us: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_micros() as u64)
+                    .unwrap_or(0),
+            };
+            let path = Path::new(&self.config.log_directory).join(format!(
+                "syscall-trace-{}-{}.bin",
+                guest.app_name, guest.runs
+            ));
+            let workload = std::fs::read(&absolute_workload_path)?;
+            Some(TraceWriter::create(path, &header, &workload)?)
+        } else {
+            None
+        };
+        let replay = self.config.syscall_replay.clone().map(TraceReplay::new);
+        guest.run.begin(
+            Path::new(&absolute_workload_path),
+            recorder,
+            replay,
+            self.faults.clone(),
+        );
+
+        // Execute workload, racing it against the timeout, resource limits and cancellation
+        let mut cancelled = self.cancel.subscribe();
+        let generation = *cancelled.borrow_and_update();
+        let timeout = self.config.timeout;
+        let measures_process = self.config.limits.measures_process();
+        let started = std::sync::OnceLock::new();
+        let limited_run = async {
+            // Memory and CPU time can only be attributed to a run while no other
+            // run of this runtime executes, so limited runs take turns. Waiting
+            // for a turn counts towards the timeout and can be cancelled.
+            let _measured_run = if measures_process {
+                Some(self.measured_run.lock().await)
+            } else {
+                None
+            };
+            let monitor = ResourceMonitor::start(
+                self.config.limits,
+                Path::new(&guest.console_path),
+                guest.console_offset,
+            );
+            let _ = started.set(Instant::now());
+            tokio::select! {
+                // The pinned nanvix returns nothing once the guest ends, so
+                // the exit status of a completed run is unknown
+                result = guest.terminal.run(
+                    Some(&script_name),
+                    Some(&guest.app_name),
+                    &effective_binary_path,
+                    &effective_script_args,
+                ) => result.map(|()| ExitStatus::unknown()).map_err(Error::GuestCrashed),
+                limit = monitor.exceeded() => {
+                    log::warn!("Workload exceeded its {}", limit);
+                    Err(Error::LimitExceeded(limit))
+                }
+            }
+        };
+        let result = tokio::select! {
+            result = limited_run => result,
+            _ = sleep_or_pending(timeout) => {
+                log::warn!("Workload timed out after {:?}", timeout.unwrap_or_default());
+                Err(Error::Timeout(timeout.unwrap_or_default()))
+            }
+            Ok(_) = cancelled.wait_for(|current| *current != generation) => {
+                log::warn!("Workload was cancelled");
+                Err(Error::Cancelled)
+            }
+        };
+        let duration = started.get().map_or(Duration::ZERO, Instant::elapsed);
+        let (recorder, replay) = guest.run.end();
+        let syscall_trace = recorder.map(TraceWriter::finish).transpose()?;
+        if let Some(path) = &syscall_trace {
+            log::info!("Syscall trace written to {}", path.display());
+        }
+        let result = match replay.map(TraceReplay::finish) {
+            Some(Err(divergence)) if result.is_ok() => Err(Error::ReplayDiverged(divergence)),
+            _ => result,
+        };
 
-        // Restore original working directory if we changed it for Python
-        if let Some(original_dir) = original_dir {
-            if let Err(e) = std::env::set_current_dir(original_dir) {
-                log::warn!("Failed to restore original working directory: {}", e);
+        // Collect the console output even if the run failed, so the shared
+        // console log stays complete.
+        let (console, output_exceeded) = self.collect_console_output(guest)?;
+        let result = match (result, self.config.limits.output) {
+            (Ok(_), Some(limit)) if output_exceeded => {
+                Err(Error::LimitExceeded(Limit::Output(limit)))
             }
+            (result, _) => result,
+        };
+        let exit_status = result?;
+        if !exit_status.success() {
+            log::info!("Guest exited with {}", exit_status);
         }
+        guest.runs += 1;
+        guest.idle_since = Instant::now();
 
-        Ok(())
+        Ok(RunOutput {
+            console,
+            exit_status,
+            duration,
+            syscall_trace,
+        })
+    }
+
+    /// Lockfile entry for a binary, unless the release pin no longer accepts it
+    fn locked_package(&self, binary_name: &str) -> Option<LockedPackage> {
+        let lockfile = self.lockfile.as_ref()?.lock().unwrap();
+        let locked = lockfile.find(&self.cache.flavor().to_string(), binary_name)?;
+        match &self.release_version {
+            Some(requirement) if !requirement.matches(&locked.release) => None,
+            _ => Some(locked.clone()),
+        }
+    }
+
+    /// Decide which release the binaries of a run may be taken from: the
+    /// locked release first, then the newest release matching the release
+    /// pin, then the newest release.
+    fn release_for(&self, binary_names: &[&str]) -> Release {
+        if let Some(locked) = binary_names
+            .iter()
+            .find_map(|binary_name| self.locked_package(binary_name))
+        {
+            return Release::Exact(locked.release);
+        }
+        match &self.release_version {
+            Some(requirement) => Release::Matching(requirement.clone()),
+            None => Release::Latest,
+        }
+    }
+
+    /// Locate the kernel and, for scripted workloads, the interpreter in one
+    /// cached release, installing the registry's current release unless
+    /// offline mode is enabled.
+    ///
+    /// The release honours the lockfile and the release pin. Every binary is
+    /// checked against its recorded digest before it is used, and against the
+    /// lockfile entry when a lockfile is configured.
+    async fn locate_binaries(
+        &self,
+        workload_type: WorkloadType,
+    ) -> Result<(LocatedBinary, Option<LocatedBinary>)> {
+        let interpreter = workload_type
+            .package_name()
+            .map(|package_name| (package_name, workload_type.binary_name()));
+        let mut binary_names = vec![KERNEL_BINARY];
+        binary_names.extend(interpreter.map(|(_, binary_name)| binary_name));
+        let release = self.release_for(&binary_names);
+
+        let mut found = self.cache.find_release_providing(&binary_names, &release);
+        // Install at most once per runtime, the registry always installs its
+        // current release, so installing again would not satisfy the pin either
+        if found.is_none()
+            && !self.config.offline
+            && !self.installed.lock().unwrap().contains(&workload_type)
+        {
+            let flavor = self.cache.flavor();
+            match interpreter {
+                Some((package_name, _)) => {
+                    log::info!("Installing package '{}' and dependencies...", package_name);
+                    self.registry
+                        .install(flavor.machine(), flavor.deployment(), package_name, true)
+                        .await
+                        .map_err(Error::RegistryUnavailable)?;
+                }
+                None => {
+                    log::info!("Downloading the Nanvix kernel...");
+                    self.registry
+                        .get_cached_binary(flavor.machine(), flavor.deployment(), KERNEL_BINARY)
+                        .await
+                        .map_err(Error::RegistryUnavailable)?;
+                }
+            }
+            self.installed.lock().unwrap().insert(workload_type);
+            found = self.cache.find_release_providing(&binary_names, &release);
+            if let Some(installed) = &found {
+                self.cache.record_installed(installed)?;
+            }
+        }
+
+        let Some(found) = found else {
+            // Report the binary missing from every acceptable release, or the
+            // interpreter when kernel and interpreter only exist in different releases
+            let missing = binary_names
+                .iter()
+                .find(|binary_name| self.cache.find_release(binary_name, &release).is_none())
+                .unwrap_or(binary_names.last().unwrap())
+                .to_string();
+            return Err(if self.config.offline {
+                Error::NotCached(missing)
+            } else if release != Release::Latest {
+                Error::VersionUnavailable {
+                    binary: missing,
+                    requirement: release.to_string(),
+                }
+            } else if missing == KERNEL_BINARY {
+                Error::KernelMissing
+            } else {
+                Error::InterpreterMissing(missing)
+            });
+        };
+
+        let mut located = found.into_iter();
+        let kernel = self.check_binary(KERNEL_PACKAGE, located.next().unwrap())?;
+        let interpreter = match (interpreter, located.next()) {
+            (Some((package_name, _)), Some(found)) => Some(self.check_binary(package_name, found)?),
+            _ => None,
+        };
+        Ok((kernel, interpreter))
+    }
+
+    /// Check a cached binary against its recorded digest and the lockfile,
+    /// recording it in the lockfile if it is not listed yet
+    fn check_binary(&self, package_name: &str, found: CachedBinary) -> Result<LocatedBinary> {
+        let binary_name = found
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let locked = self.locked_package(&binary_name);
+        let sha256 = self.cache.verify(&found)?;
+        self.cache.mark_used(&found);
+        if let Some(lockfile) = &self.lockfile {
+            match locked {
+                Some(locked) if locked.release != found.release || locked.sha256 != sha256 => {
+                    return Err(Error::LockfileMismatch {
+                        binary: binary_name,
+                        release: found.release,
+                    });
+                }
+                Some(_) => {}
+                None => lockfile.lock().unwrap().record(LockedPackage {
+                    name: package_name.to_string(),
+                    binary: binary_name,
+                    flavor: self.cache.flavor().to_string(),
+                    release: found.release.clone(),
+                    sha256: sha256.clone(),
+                })?,
+            }
+        }
+
+        Ok(LocatedBinary {
+            path: found.path.to_string_lossy().into_owned(),
+            sha256,
+        })
     }
 
-    fn prepare_script_args(
+    /// Read the console output a guest produced since its previous run and
+    /// append it to `{log_directory}/guest-console.log`.
+    ///
+    /// Output beyond the output limit is discarded, the returned flag tells
+    /// whether the limit was exceeded.
+    fn collect_console_output(&self, guest: &mut Guest) -> Result<(String, bool)> {
+        use std::io::{Read, Seek, SeekFrom, Write};
+
+        let mut console = match std::fs::File::open(&guest.console_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok((String::new(), false))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        console.seek(SeekFrom::Start(guest.console_offset))?;
+        let mut output = Vec::new();
+        match self.config.limits.output {
+            Some(limit) => (&mut console).take(limit + 1).read_to_end(&mut output)?,
+            None => console.read_to_end(&mut output)?,
+        };
+        let exceeded = self
+            .config
+            .limits
+            .output
+            .is_some_and(|limit| output.len() as u64 > limit);
+        if let Some(limit) = self.config.limits.output {
+            output.truncate(limit as usize);
+        }
+        guest.console_offset = console.seek(SeekFrom::End(0))?;
+
+        let console_log_path = format!("{}/guest-console.log", &self.config.log_directory);
+        let mut console_log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&console_log_path)?;
+        console_log.write_all(&output)?;
+
+        Ok((String::from_utf8_lossy(&output).into_owned(), exceeded))
+    }
+
+    fn prepare_script_args<S: AsRef<str>>(
         &self,
         workload_type: WorkloadType,
         workload_path: &Path,
+        user_args: &[S],
     ) -> Result<(String, String)> {
         let script_name = workload_path
             .file_name()
             .and_then(|name| name.to_str())
-            .ok_or_else(|| anyhow::anyhow!("Invalid workload path: {:?}", workload_path))?
+            .ok_or_else(|| {
+                Error::InvalidWorkload(format!("Invalid workload path: {:?}", workload_path))
+            })?
             .to_string();
 
-        let script_args = match workload_type {
-            WorkloadType::JavaScript => {
-                let mut args = workload_path.to_string_lossy().to_string();
-                args.insert_str(0, "-m ");
-                args
-            }
-            WorkloadType::Python => {
-                format!("-S -I {}", workload_path.to_string_lossy())
-            }
-            WorkloadType::Binary => {
-                // Binary files are executed directly, no script args needed
-                String::new()
-            }
+        let quoted_path = quote_arg(&workload_path.to_string_lossy()).into_owned();
+        let mut args: Vec<String> = match workload_type {
+            WorkloadType::JavaScript => vec!["-m".to_string(), quoted_path],
+            WorkloadType::Python => vec!["-S".to_string(), "-I".to_string(), quoted_path],
+            // Binary files are executed directly, so only user arguments are passed
+            WorkloadType::Binary => Vec::new(),
         };
+        args.extend(
+            user_args
+                .iter()
+                .map(|arg| quote_arg(arg.as_ref()).into_owned()),
+        );
+
+        Ok((args.join(" "), script_name))
+    }
+}
+
+/// A verified cached binary
+struct LocatedBinary {
+    path: String,
+    sha256: String,
+}
+
+/// Paths an interpreter reads whatever the policy says: its binary and the
+/// standard library in the `lib/` directory of its release
+fn interpreter_files(binary_path: &str) -> Vec<PathBuf> {
+    if binary_path.is_empty() {
+        return Vec::new();
+    }
+    let binary = PathBuf::from(binary_path);
+    let stdlib = binary
+        .parent()
+        .filter(|directory| directory.ends_with("bin"))
+        .and_then(Path::parent)
+        .map(|release| release.join("lib"));
+    std::iter::once(binary).chain(stdlib).collect()
+}
+
+/// Source file staged by [`Runtime::run_source`], removed when dropped
+pub(crate) struct TempSource(pub(crate) PathBuf);
+
+impl Drop for TempSource {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            log::warn!("Failed to remove source file {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Sleep for the given duration, or forever if there is none
+async fn sleep_or_pending(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// A guest terminal prepared by [`Runtime::prepare_guest`].
+///
+/// The terminal is declared first so that the guest is torn down before its
+/// leftovers are cleaned up.
+pub(crate) struct Guest {
+    terminal: Terminal<GuestSyscalls>,
+    _cleanup: RunCleanup,
+    workload_type: WorkloadType,
+    binary_path: String,
+    snapshot_key: Option<SnapshotKey>,
+    run: RunState,
+    app_name: String,
+    console_path: String,
+    console_offset: u64,
+    runs: u32,
+    idle_since: Instant,
+}
+
+impl Guest {
+    /// Workload type the guest was prepared for
+    pub(crate) fn workload_type(&self) -> WorkloadType {
+        self.workload_type
+    }
+
+    /// Number of completed runs
+    pub(crate) fn runs(&self) -> u32 {
+        self.runs
+    }
+
+    /// Stop counting the runs completed so far, e.g. a warm-up run
+    pub(crate) fn reset_runs(&mut self) {
+        self.runs = 0;
+    }
+
+    /// How long the guest has been waiting for its next run
+    pub(crate) fn idle_for(&self) -> Duration {
+        self.idle_since.elapsed()
+    }
+}
+
+/// Removes the directory holding the console file and Unix sockets of a
+/// guest, including the sockets left behind by a run that did not complete
+/// normally.
+///
+/// The directory belongs to a single guest, so other sandboxes sharing the
+/// temporary directory are left alone.
+struct RunCleanup {
+    guest_directory: PathBuf,
+}
+
+impl RunCleanup {
+    fn new(guest_directory: &str) -> Self {
+        Self {
+            guest_directory: PathBuf::from(guest_directory),
+        }
+    }
+}
+
+impl Drop for RunCleanup {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.guest_directory) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!(
+                    "Failed to remove guest directory {}: {}",
+                    self.guest_directory.display(),
+                    e
+                );
+            }
+        }
+    }
+}
 
-        Ok((script_args, script_name))
+/// Quote a single argument so that it survives splitting of the guest command line.
+///
+/// Arguments made only of shell-safe characters are passed through unchanged;
+/// anything else is wrapped in single quotes, with embedded single quotes escaped.
+/// This relies on the guest splitting its command line with POSIX shell quoting,
+/// which `test_quoted_arguments_reach_guest_unchanged` checks end to end.
+pub(crate) fn quote_arg(arg: &str) -> std::borrow::Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        std::borrow::Cow::Borrowed(arg)
+    } else {
+        std::borrow::Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''")))
     }
 }
@@ -0,0 +1,262 @@
+use std::path::{Path, PathBuf};
+
+use nanvix::log;
+
+use crate::cache::Flavor;
+use crate::error::{Error, Result};
+use crate::integrity::DigestStore;
+use crate::runtime::WorkloadType;
+
+/// File extension of persisted guest snapshots
+const SNAPSHOT_EXTENSION: &str = "snapshot";
+
+/// Return the default directory for persisted guest snapshots.
+///
+/// Snapshots live in the per-user cache directory (e.g.
+/// `~/.cache/hyperlight-nanvix/snapshots` on Linux), next to but outside of
+/// the nanvix-registry cache.
+pub fn default_snapshot_directory() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("hyperlight-nanvix")
+        .join("snapshots")
+}
+
+/// Identifies the binaries a guest snapshot was taken with.
+///
+/// A snapshot is only valid for the exact kernel and interpreter it was taken
+/// from, so the key includes the SHA-256 digests of both. A changed binary
+/// yields a different key and the stale snapshot is never restored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SnapshotKey {
+    workload_type: WorkloadType,
+    flavor: Flavor,
+    kernel_sha256: String,
+    interpreter_sha256: String,
+}
+
+impl SnapshotKey {
+    pub fn new<K: Into<String>, I: Into<String>>(
+        workload_type: WorkloadType,
+        flavor: Flavor,
+        kernel_sha256: K,
+        interpreter_sha256: I,
+    ) -> Self {
+        Self {
+            workload_type,
+            flavor,
+            kernel_sha256: kernel_sha256.into(),
+            interpreter_sha256: interpreter_sha256.into(),
+        }
+    }
+
+    /// Workload type whose interpreter the snapshot was taken with
+    pub fn workload_type(&self) -> WorkloadType {
+        self.workload_type
+    }
+
+    /// File name of the snapshot, e.g. `python3-hyperlight-single-process-<digest>.snapshot`
+    pub fn file_name(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.kernel_sha256.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(self.interpreter_sha256.as_bytes());
+        let digest: String = hasher
+            .finalize()
+            .iter()
+            .take(16)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("{}{}.{}", self.prefix(), digest, SNAPSHOT_EXTENSION)
+    }
+
+    /// Common prefix of all snapshots taken for the same workload type and flavor
+    fn prefix(&self) -> String {
+        format!(
+            "{}-{}-{}-",
+            self.workload_type.binary_name(),
+            self.flavor.machine(),
+            self.flavor.deployment()
+        )
+    }
+
+    /// Returns `true` if `file_name` is a snapshot of the same workload type and
+    /// flavor as this key, whichever binaries it was taken with
+    fn is_sibling(&self, file_name: &str) -> bool {
+        file_name
+            .strip_prefix(&self.prefix())
+            .and_then(|rest| rest.strip_suffix(&format!(".{}", SNAPSHOT_EXTENSION)))
+            .is_some_and(|digest| digest.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+}
+
+/// Snapshot being written by a guest, removed when dropped unless committed
+pub(crate) struct StagedSnapshot(PathBuf);
+
+impl StagedSnapshot {
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for StagedSnapshot {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove snapshot {}: {}", self.0.display(), e);
+            }
+        }
+    }
+}
+
+/// Directory of persisted guest snapshots.
+///
+/// Snapshots are checked against the digest recorded when they were taken
+/// before they are restored, like cached binaries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotStore {
+    directory: PathBuf,
+    digests: DigestStore,
+}
+
+impl Default for SnapshotStore {
+    fn default() -> Self {
+        Self::new(default_snapshot_directory())
+    }
+}
+
+impl SnapshotStore {
+    /// Use the snapshots stored in the given directory
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+            digests: DigestStore::default(),
+        }
+    }
+
+    /// Record and verify snapshot digests in a specific digest store
+    pub fn with_digest_store(mut self, digests: DigestStore) -> Self {
+        self.digests = digests;
+        self
+    }
+
+    /// Directory holding the snapshots
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Path of the snapshot for a key, whether or not it exists
+    pub fn path(&self, key: &SnapshotKey) -> PathBuf {
+        self.directory.join(key.file_name())
+    }
+
+    /// Find the snapshot for a key, verifying it against its recorded digest.
+    ///
+    /// A snapshot without a recorded digest was not taken by this store, e.g.
+    /// one dropped into the directory, so it is never restored and the next
+    /// snapshot taken for the key replaces it.
+    pub fn find(&self, key: &SnapshotKey) -> Result<Option<PathBuf>> {
+        let path = self.path(key);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        match self.digests.verify(&path) {
+            Ok(_) => Ok(Some(path)),
+            Err(Error::DigestMissing(_)) => {
+                log::warn!(
+                    "Ignoring snapshot {} without a recorded digest",
+                    path.display()
+                );
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// List the snapshots in the store, sorted by file name
+    pub fn list(&self) -> Result<Vec<PathBuf>> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut snapshots: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|extension| extension == SNAPSHOT_EXTENSION)
+                    && !path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            })
+            .collect();
+        snapshots.sort();
+        Ok(snapshots)
+    }
+
+    /// Remove every snapshot in the store, returning how many were removed
+    pub fn clear(&self) -> Result<usize> {
+        let snapshots = self.list()?;
+        for snapshot in &snapshots {
+            std::fs::remove_file(snapshot)?;
+        }
+        self.digests.forget_under(&self.directory)?;
+        Ok(snapshots.len())
+    }
+
+    /// Reserve a hidden file for a guest to write the snapshot for a key into.
+    ///
+    /// The file must not exist, or the guest would restore it instead of
+    /// taking a new snapshot.
+    pub(crate) fn stage(&self, key: &SnapshotKey) -> Result<StagedSnapshot> {
+        std::fs::create_dir_all(&self.directory)?;
+        let unique_id = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let path = self.directory.join(format!(
+            ".{}.partial-{}-{}",
+            key.file_name(),
+            std::process::id(),
+            unique_id
+        ));
+        if path.exists() {
+            return Err(Error::SnapshotFailed(format!(
+                "staged snapshot {} already exists",
+                path.display()
+            )));
+        }
+        Ok(StagedSnapshot(path))
+    }
+
+    /// Move a staged snapshot into place, record its digest and remove the
+    /// snapshots taken with other binaries for the same workload type
+    pub(crate) fn commit(&self, key: &SnapshotKey, staged: StagedSnapshot) -> Result<PathBuf> {
+        if !staged.path().is_file() {
+            return Err(Error::SnapshotFailed(format!(
+                "the guest did not write a snapshot to {}",
+                staged.path().display()
+            )));
+        }
+
+        let path = self.path(key);
+        std::fs::rename(staged.path(), &path)?;
+        self.digests.record(&path)?;
+
+        let file_name = key.file_name();
+        for stale in self.list()? {
+            let name = stale.file_name().unwrap_or_default().to_string_lossy();
+            if name != file_name && key.is_sibling(&name) {
+                log::info!("Removing stale snapshot {}", stale.display());
+                std::fs::remove_file(&stale)?;
+                self.digests.forget_under(&stale)?;
+            }
+        }
+        Ok(path)
+    }
+}
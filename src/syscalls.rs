@@ -0,0 +1,453 @@
+use std::ffi::{CStr, CString, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use nanvix::sandbox::{SyscallAction, SyscallTable};
+
+use crate::audit::{openat_args, AuditSink, SyscallEvent};
+use crate::error::{Error, Result};
+use crate::faults::FaultInjector;
+use crate::hooks::SyscallHandler;
+use crate::policy::{canonical_or_normalized, Access, PolicyRule, SyscallPolicy};
+use crate::trace::{Replayed, TraceReplay, TraceWriter, MAX_CONTENT_LENGTH};
+
+/// State of the run a guest is executing, shared with its syscall handlers
+#[derive(Clone, Default)]
+pub(crate) struct RunState(Arc<Mutex<Run>>);
+
+#[derive(Default)]
+struct Run {
+    /// The interpreter has to read the script it runs, so the workload stays
+    /// readable whatever the policy of the guest says
+    workload: Option<PathBuf>,
+    recorder: Option<TraceWriter>,
+    replay: Option<TraceReplay>,
+    faults: Option<FaultInjector>,
+}
+
+impl RunState {
+    /// Start a run of `workload`, optionally recording, replaying or injecting
+    /// faults into its syscalls
+    pub(crate) fn begin(
+        &self,
+        workload: &Path,
+        recorder: Option<TraceWriter>,
+        replay: Option<TraceReplay>,
+        faults: Option<FaultInjector>,
+    ) {
+        *self.lock() = Run {
+            workload: Some(canonical_or_normalized(workload)),
+            recorder,
+            replay,
+            faults,
+        };
+    }
+
+    /// End the run, handing back its recorder and replay
+    pub(crate) fn end(&self) -> (Option<TraceWriter>, Option<TraceReplay>) {
+        let run = std::mem::take(&mut *self.lock());
+        (run.recorder, run.replay)
+    }
+
+    fn is_workload(&self, path: &Path) -> bool {
+        self.lock().workload.as_deref() == Some(path)
+    }
+
+    fn is_recording(&self) -> bool {
+        self.lock().recorder.is_some()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Run> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// State handed to the syscall handlers the runtime installs in every guest.
+///
+/// The runtime always installs its own table, so that policies can be
+/// enforced and syscalls audited per guest. Hooks supplied with
+/// `RuntimeConfig::with_syscall_hooks` and handlers of a table supplied with
+/// `RuntimeConfig::with_syscall_table` are still called, in that order, for
+/// the syscalls the policy allows, with paths already translated to the host.
+pub(crate) struct GuestSyscalls {
+    table: Option<Arc<SyscallTable<()>>>,
+    hooks: Option<Arc<dyn SyscallHandler>>,
+    policy: Option<SyscallPolicy>,
+    trusted: Vec<PathBuf>,
+    run: RunState,
+    per_run: bool,
+    audit: Option<Arc<dyn AuditSink>>,
+}
+
+impl GuestSyscalls {
+    /// Combine an embedder's syscall table with a policy.
+    ///
+    /// `trusted` paths (the interpreter binary and its standard library) stay
+    /// readable whatever the policy says, like
+    /// the workload of the current run, so the interpreter can still start.
+    /// Nothing else in the registry cache or the temporary directory is exempt.
+    pub(crate) fn new(
+        table: Option<Arc<SyscallTable<()>>>,
+        policy: Option<&SyscallPolicy>,
+        trusted: &[PathBuf],
+        run: RunState,
+    ) -> Self {
+        let policy = policy.map(|policy| SyscallPolicy {
+            default: policy.default,
+            rules: policy
+                .rules
+                .iter()
+                .map(|rule| PolicyRule {
+                    path: rule.path.as_deref().map(canonical_or_normalized),
+                    ..rule.clone()
+                })
+                .collect(),
+        });
+        Self {
+            table,
+            hooks: None,
+            policy,
+            trusted: trusted
+                .iter()
+                .map(|path| canonical_or_normalized(path))
+                .collect(),
+            run,
+            per_run: false,
+            audit: None,
+        }
+    }
+
+    /// Call safe hooks before the embedder's raw syscall table
+    pub(crate) fn with_hooks(mut self, hooks: Option<Arc<dyn SyscallHandler>>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Intercept every syscall so runs can be recorded, replayed or injected with faults
+    pub(crate) fn with_run_interception(mut self, per_run: bool) -> Self {
+        self.per_run = per_run;
+        self
+    }
+
+    /// Record every syscall of the guest in an audit sink
+    pub(crate) fn with_audit(mut self, audit: Option<Arc<dyn AuditSink>>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Build the syscall table for a guest.
+    ///
+    /// Every entry of the embedder's table is carried over. The table is
+    /// destructured exhaustively, so a nanvix release that adds an entry
+    /// fails to build here until the entry is wrapped like `openat` and
+    /// listed in [`INTERCEPTABLE_SYSCALLS`](crate::policy::INTERCEPTABLE_SYSCALLS).
+    pub(crate) fn into_table(self) -> Arc<SyscallTable<GuestSyscalls>> {
+        let forwards_openat = match self.table.as_deref() {
+            Some(SyscallTable { state: (), openat }) => matches!(openat, SyscallAction::Forward(_)),
+            None => false,
+        };
+        let intercepts_openat = self.confines_paths()
+            || self.per_run
+            || self.audit.is_some()
+            || self
+                .hooks
+                .as_ref()
+                .is_some_and(|hooks| hooks.handles("openat"))
+            || forwards_openat;
+
+        let mut table = SyscallTable::new(self);
+        if intercepts_openat {
+            table.openat = SyscallAction::Forward(openat);
+        }
+        Arc::new(table)
+    }
+
+    fn is_trusted(&self, host_path: &Path) -> bool {
+        self.trusted
+            .iter()
+            .any(|directory| host_path.starts_with(directory))
+    }
+
+    fn confines_paths(&self) -> bool {
+        self.policy.is_some()
+    }
+
+    /// Decide which host path a guest `openat` reaches, or the error number it fails with.
+    ///
+    /// Guest paths are host paths, resolved against the working directory or
+    /// the directory descriptor they are relative to.
+    fn resolve(
+        &self,
+        dirfd: i32,
+        pathname: &Path,
+        access: Access,
+    ) -> std::result::Result<PathBuf, i32> {
+        let host_path = resolve_path(dirfd, pathname);
+        if access == Access::ReadOnly
+            && (self.run.is_workload(&host_path) || self.is_trusted(&host_path))
+        {
+            return Ok(host_path);
+        }
+
+        if let Some(policy) = &self.policy {
+            let action = policy.evaluate("openat", Some(&host_path), access);
+            if let Some(errno) = action.errno() {
+                nanvix::log::debug!("Policy {} openat of {}", action, host_path.display());
+                return Err(errno);
+            }
+        }
+        Ok(host_path)
+    }
+}
+
+/// Apply the guest's policy to `openat`, forward it to the
+/// embedder's handler or the host, and audit or record the outcome.
+///
+/// A replayed run gets the recorded outcomes instead, and injected faults
+/// fail the call before it reaches the policy.
+unsafe fn openat(
+    state: &GuestSyscalls,
+    dirfd: i32,
+    pathname: *const i8,
+    flags: i32,
+    mode: u32,
+) -> i32 {
+    let timestamp = SystemTime::now();
+    let requested = Path::new(OsStr::from_bytes(CStr::from_ptr(pathname).to_bytes()));
+    let (result, host_path) = match replay_openat(state, dirfd, requested) {
+        Some(result) => (result, None),
+        None => match inject_fault(state, dirfd, requested) {
+            Some(errno) => (fail(errno), None),
+            None => confined_openat(state, dirfd, requested, pathname, flags, mode),
+        },
+    };
+
+    let recording = state.per_run && state.run.is_recording();
+    if state.audit.is_some() || recording {
+        let errno = (result < 0).then(|| *libc::__errno_location());
+        let host_path = match host_path {
+            None if !state.confines_paths() => Some(resolve_path(dirfd, requested)),
+            host_path => host_path,
+        };
+        let args = openat_args(dirfd, requested, flags, mode);
+        let event = SyscallEvent::new(timestamp, "openat", args, result.into(), errno)
+            .with_host_path(host_path.as_deref());
+        if let Some(audit) = &state.audit {
+            audit.record(&event);
+        }
+        if recording {
+            // Files other than the interpreter and its standard library may
+            // change before the run is replayed, so their content is kept
+            let content = host_path
+                .filter(|path| state.run.is_workload(path) || !state.is_trusted(path))
+                .and_then(|_| file_content(result));
+            if let Some(recorder) = state.run.lock().recorder.as_mut() {
+                recorder.record(&event, content.as_deref());
+            }
+        }
+        // The sink may have clobbered errno
+        if let Some(errno) = errno {
+            fail(errno);
+        }
+    }
+    result
+}
+
+/// Answer `openat` from the trace being replayed, `None` if the call should be performed
+fn replay_openat(state: &GuestSyscalls, dirfd: i32, requested: &Path) -> Option<i32> {
+    if !state.per_run {
+        return None;
+    }
+    let is_workload = state.run.is_workload(&resolve_path(dirfd, requested));
+    let mut run = state.run.lock();
+    match run.replay.as_mut()?.openat(requested, is_workload) {
+        Replayed::Failed(errno) => Some(fail(errno)),
+        Replayed::Content(content) => Some(memory_file(content).unwrap_or_else(fail)),
+        Replayed::Perform => None,
+    }
+}
+
+/// Error number of an injected fault failing `openat`, matched against the guest's path.
+///
+/// Faults are never injected into opens of the workload, the interpreter or
+/// its standard library, so the guest can still start.
+fn inject_fault(state: &GuestSyscalls, dirfd: i32, requested: &Path) -> Option<i32> {
+    if !state.per_run || state.run.lock().faults.is_none() {
+        return None;
+    }
+    let host_path = resolve_path(dirfd, requested);
+    if state.run.is_workload(&host_path) || state.is_trusted(&host_path) {
+        return None;
+    }
+    let errno = state
+        .run
+        .lock()
+        .faults
+        .as_mut()?
+        .inject("openat", Some(requested))?;
+    nanvix::log::debug!("Injected fault into openat of {}", requested.display());
+    Some(errno)
+}
+
+/// Content of a regular file opened by the guest, `None` for files too large
+/// to be kept in a trace
+fn file_content(fd: i32) -> Option<Vec<u8>> {
+    if fd < 0 {
+        return None;
+    }
+    // Reopen the file rather than reading the guest's descriptor, whose offset must not move
+    let path = format!("/proc/self/fd/{}", fd);
+    std::fs::metadata(&path)
+        .ok()
+        .filter(|metadata| metadata.is_file() && metadata.len() <= MAX_CONTENT_LENGTH)?;
+    std::fs::read(path).ok()
+}
+
+/// Create an anonymous file holding `content`, returning its descriptor or an error number
+fn memory_file(content: &[u8]) -> std::result::Result<i32, i32> {
+    use std::io::{Seek, Write};
+    use std::os::fd::{FromRawFd, IntoRawFd};
+
+    // SAFETY: the name is a valid NUL-terminated string
+    let fd = unsafe { libc::memfd_create(c"hyperlight-nanvix-replay".as_ptr(), 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error()
+            .raw_os_error()
+            .unwrap_or(libc::EIO));
+    }
+    // SAFETY: the descriptor was just created and is owned by nothing else
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+    file.write_all(content)
+        .and_then(|_| file.rewind())
+        .map_err(|e| e.raw_os_error().unwrap_or(libc::EIO))?;
+    Ok(file.into_raw_fd())
+}
+
+/// Perform `openat` within the guest's policy, returning the host path it
+/// was checked against.
+///
+/// The call is made on exactly that path rather than the guest's arguments,
+/// so the path that was checked is the one that is opened.
+unsafe fn confined_openat(
+    state: &GuestSyscalls,
+    dirfd: i32,
+    requested: &Path,
+    pathname: *const i8,
+    flags: i32,
+    mode: u32,
+) -> (i32, Option<PathBuf>) {
+    if !state.confines_paths() {
+        return (forward_openat(state, dirfd, pathname, flags, mode), None);
+    }
+
+    let host_path = match state.resolve(dirfd, requested, Access::from_open_flags(flags)) {
+        Ok(host_path) => host_path,
+        Err(errno) => return (fail(errno), None),
+    };
+
+    // Every symbolic link was resolved by the check, so a last component
+    // swapped for a link since is not followed. Directories above it can
+    // still be swapped by whoever may write to their parents.
+    let Ok(checked) = CString::new(host_path.as_os_str().as_bytes()) else {
+        return (fail(libc::EINVAL), Some(host_path));
+    };
+    let result = forward_openat(
+        state,
+        libc::AT_FDCWD,
+        checked.as_ptr(),
+        flags | libc::O_NOFOLLOW,
+        mode,
+    );
+    (result, Some(host_path))
+}
+
+/// Pass `openat` to the embedder's hooks, then its raw syscall table or the host
+unsafe fn forward_openat(
+    state: &GuestSyscalls,
+    dirfd: i32,
+    pathname: *const i8,
+    flags: i32,
+    mode: u32,
+) -> i32 {
+    let Some(hooks) = state
+        .hooks
+        .as_deref()
+        .filter(|hooks| hooks.handles("openat"))
+    else {
+        return raw_openat(state, dirfd, pathname, flags, mode);
+    };
+    let next = |dirfd, pathname: &CStr, flags, mode| {
+        // SAFETY: `pathname` is a valid NUL-terminated string for the duration of the call
+        match unsafe { raw_openat(state, dirfd, pathname.as_ptr(), flags, mode) } {
+            fd if fd < 0 => Err(*libc::__errno_location()),
+            fd => Ok(fd),
+        }
+    };
+    match hooks.openat(dirfd, CStr::from_ptr(pathname), flags, mode, &next) {
+        Ok(result) => result,
+        Err(errno) => fail(errno),
+    }
+}
+
+/// Chain to the action of the embedder's raw table: its `Forward` handler, or
+/// the host call nanvix performs for the default action.
+///
+/// [`check_embedder_table`] rejects every other action up front, as nanvix
+/// carries those out itself and they cannot be chained to.
+unsafe fn raw_openat(
+    state: &GuestSyscalls,
+    dirfd: i32,
+    pathname: *const i8,
+    flags: i32,
+    mode: u32,
+) -> i32 {
+    match state.table.as_deref().map(|table| &table.openat) {
+        Some(SyscallAction::Forward(handler)) => handler(&(), dirfd, pathname, flags, mode),
+        _ => libc::openat(dirfd, pathname, flags, mode),
+    }
+}
+
+/// Check that every action of an embedder's syscall table can be chained to.
+///
+/// The runtime wraps each entry and calls the embedder's handler once the
+/// policy, hooks and other layers are done, which only works for `Forward`
+/// handlers and the default action. Any other action is rejected rather than
+/// silently bypassed.
+pub(crate) fn check_embedder_table(table: &SyscallTable<()>) -> Result<()> {
+    let SyscallTable { state: (), openat } = table;
+    let default = SyscallTable::new(());
+    if matches!(openat, SyscallAction::Forward(_))
+        || std::mem::discriminant(openat) == std::mem::discriminant(&default.openat)
+    {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedSyscallAction("openat".to_string()))
+    }
+}
+
+/// Fail a syscall with an error number
+fn fail(errno: i32) -> i32 {
+    // SAFETY: errno is thread-local and always writable
+    unsafe { *libc::__errno_location() = errno };
+    -1
+}
+
+/// Resolve the path a `*at` syscall refers to on the host.
+///
+/// Symbolic links are resolved as far as the path exists, so a link cannot be
+/// used to escape the directories a policy allows.
+fn resolve_path(dirfd: i32, pathname: &Path) -> PathBuf {
+    let path = if pathname.is_absolute() {
+        pathname.to_path_buf()
+    } else {
+        let base = if dirfd == libc::AT_FDCWD {
+            std::env::current_dir().unwrap_or_default()
+        } else {
+            std::fs::read_link(format!("/proc/self/fd/{}", dirfd)).unwrap_or_default()
+        };
+        base.join(pathname)
+    };
+    canonical_or_normalized(&path)
+}
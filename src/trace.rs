@@ -0,0 +1,287 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::SyscallEvent;
+use crate::error::{Error, Result};
+
+/// First bytes of every trace file, including the format version
+const TRACE_MAGIC: &[u8; 8] = b"HLNXTRC1";
+
+/// Content length marking a frame without content
+const NO_CONTENT: u64 = u64::MAX;
+
+/// Largest JSON object of a frame; headers and events are far smaller
+const MAX_JSON_LENGTH: usize = 1 << 20;
+
+/// Largest file content kept in a trace, bounding what loading one allocates
+pub(crate) const MAX_CONTENT_LENGTH: u64 = 1 << 30;
+
+/// What a trace records about the run it was taken from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceHeader {
+    /// Absolute path of the workload when it was recorded
+    pub workload_path: PathBuf,
+    /// Arguments passed to the workload
+    pub args: Vec<String>,
+    /// When the run started, in microseconds since the Unix epoch
+    pub recorded_at_us: u64,
+}
+
+/// A syscall of a recorded run and the content of the file it opened
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceRecord {
+    pub event: SyscallEvent,
+    /// Content of the file at the time it was opened, for regular files other than the interpreter
+    /// and its standard library
+    pub content: Option<Vec<u8>>,
+}
+
+/// The syscalls of a recorded run, loaded from a trace file.
+///
+/// A trace starts with a header and the workload itself, followed by one
+/// record per syscall. Every frame is a length-prefixed JSON object followed
+/// by a length-prefixed blob, so traces can be inspected with ordinary tools.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyscallTrace {
+    pub header: TraceHeader,
+    /// Content of the workload when it was recorded
+    pub workload: Vec<u8>,
+    pub records: Vec<TraceRecord>,
+}
+
+impl SyscallTrace {
+    /// Load a trace written by a run with syscall recording enabled
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let invalid =
+            |reason: String| Error::InvalidTrace(format!("{}: {}", path.display(), reason));
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; TRACE_MAGIC.len()];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|e| invalid(e.to_string()))?;
+        if &magic != TRACE_MAGIC {
+            return Err(invalid("not a syscall trace".to_string()));
+        }
+
+        let (header, workload) = read_frame::<TraceHeader>(&mut reader)
+            .map_err(|e| invalid(e.to_string()))?
+            .ok_or_else(|| invalid("missing header".to_string()))?;
+        let mut records = Vec::new();
+        while let Some((event, content)) =
+            read_frame::<SyscallEvent>(&mut reader).map_err(|e| invalid(e.to_string()))?
+        {
+            records.push(TraceRecord { event, content });
+        }
+
+        Ok(Self {
+            header,
+            workload: workload.unwrap_or_default(),
+            records,
+        })
+    }
+}
+
+/// Read a frame, `None` at the end of the trace
+fn read_frame<T: DeserializeOwned>(
+    reader: &mut impl Read,
+) -> std::io::Result<Option<(T, Option<Vec<u8>>)>> {
+    let mut length = [0; 4];
+    match reader.read_exact(&mut length) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_JSON_LENGTH {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "frame of {} bytes exceeds {} bytes",
+                length, MAX_JSON_LENGTH
+            ),
+        ));
+    }
+    let mut json = vec![0; length];
+    reader.read_exact(&mut json)?;
+    let value = serde_json::from_slice(&json)?;
+
+    let mut length = [0; 8];
+    reader.read_exact(&mut length)?;
+    let content = match u64::from_le_bytes(length) {
+        NO_CONTENT => None,
+        length if length > MAX_CONTENT_LENGTH => {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "content of {} bytes exceeds {} bytes",
+                    length, MAX_CONTENT_LENGTH
+                ),
+            ))
+        }
+        length => {
+            let mut content = Vec::new();
+            reader.take(length).read_to_end(&mut content)?;
+            if content.len() as u64 != length {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            Some(content)
+        }
+    };
+    Ok(Some((value, content)))
+}
+
+fn write_frame<T: Serialize>(
+    writer: &mut impl Write,
+    value: &T,
+    content: Option<&[u8]>,
+) -> std::io::Result<()> {
+    let json = serde_json::to_vec(value)?;
+    let length = u32::try_from(json.len()).map_err(|_| ErrorKind::InvalidInput)?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(&json)?;
+    match content {
+        Some(content) => {
+            writer.write_all(&(content.len() as u64).to_le_bytes())?;
+            writer.write_all(content)
+        }
+        None => writer.write_all(&NO_CONTENT.to_le_bytes()),
+    }
+}
+
+/// Writes the trace of a run as its syscalls happen
+pub(crate) struct TraceWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    error: Option<std::io::Error>,
+}
+
+impl TraceWriter {
+    pub(crate) fn create(path: PathBuf, header: &TraceHeader, workload: &[u8]) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writer.write_all(TRACE_MAGIC)?;
+        write_frame(&mut writer, header, Some(workload))?;
+        Ok(Self {
+            path,
+            writer,
+            error: None,
+        })
+    }
+
+    /// Append a syscall; the first write error is reported by [`TraceWriter::finish`]
+    pub(crate) fn record(&mut self, event: &SyscallEvent, content: Option<&[u8]>) {
+        if self.error.is_none() {
+            self.error = write_frame(&mut self.writer, event, content).err();
+        }
+    }
+
+    /// Flush the trace and return its path
+    pub(crate) fn finish(mut self) -> Result<PathBuf> {
+        if let Some(error) = self.error.take() {
+            return Err(error.into());
+        }
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+}
+
+/// How a replayed syscall is answered
+pub(crate) enum Replayed<'a> {
+    /// Fail with the recorded error number
+    Failed(i32),
+    /// Open a file holding the recorded content
+    Content(&'a [u8]),
+    /// Perform the syscall, nothing was recorded that would change its outcome
+    Perform,
+}
+
+/// Feeds the recorded outcomes of a trace back to a guest, in order
+pub(crate) struct TraceReplay {
+    trace: Arc<SyscallTrace>,
+    next: usize,
+    divergence: Option<String>,
+}
+
+impl TraceReplay {
+    pub(crate) fn new(trace: Arc<SyscallTrace>) -> Self {
+        Self {
+            trace,
+            next: 0,
+            divergence: None,
+        }
+    }
+
+    /// Answer the next `openat` of the guest.
+    ///
+    /// The workload is staged at a different path when it is replayed, so an
+    /// open of the current workload matches an open of the recorded one.
+    pub(crate) fn openat(&mut self, pathname: &Path, is_workload: bool) -> Replayed<'_> {
+        let index = self.next;
+        self.next += 1;
+        let Some(record) = self.trace.records.get(index) else {
+            self.diverge(format!(
+                "the guest made more than the {} recorded syscalls",
+                self.trace.records.len()
+            ));
+            return Replayed::Failed(libc::EIO);
+        };
+
+        let recorded_path = record
+            .event
+            .args
+            .get("pathname")
+            .and_then(|path| path.as_str());
+        let recorded_workload = record
+            .event
+            .host_path
+            .as_deref()
+            .is_some_and(|path| Path::new(path) == self.trace.header.workload_path);
+        let matches = record.event.syscall == "openat"
+            && (recorded_path.is_some_and(|path| Path::new(path) == pathname)
+                || is_workload && recorded_workload);
+        if !matches {
+            let message = format!(
+                "syscall {} is openat of {}, but {} of {} was recorded",
+                index,
+                pathname.display(),
+                record.event.syscall,
+                recorded_path.unwrap_or("?")
+            );
+            self.diverge(message);
+            return Replayed::Failed(libc::EIO);
+        }
+
+        let record = &self.trace.records[index];
+        match (record.event.errno, &record.content) {
+            (Some(errno), _) => Replayed::Failed(errno),
+            (None, Some(content)) => Replayed::Content(content),
+            (None, None) => Replayed::Perform,
+        }
+    }
+
+    fn diverge(&mut self, message: String) {
+        if self.divergence.is_none() {
+            self.divergence = Some(message);
+        }
+    }
+
+    /// Report where the run diverged from the trace, if it did
+    pub(crate) fn finish(self) -> std::result::Result<(), String> {
+        if let Some(divergence) = self.divergence {
+            return Err(divergence);
+        }
+        if self.next < self.trace.records.len() {
+            return Err(format!(
+                "the guest made {} of the {} recorded syscalls",
+                self.next,
+                self.trace.records.len()
+            ));
+        }
+        Ok(())
+    }
+}
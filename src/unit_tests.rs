@@ -2,7 +2,57 @@
 mod tests {
     use crate::runtime::{Runtime, WorkloadType};
     use crate::*;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Temporary directory of a test, removed when dropped even if the test panics.
+    ///
+    /// The name holds the process id and a counter, so neither tests running
+    /// in parallel nor other test binaries ever share a directory.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "hyperlight-nanvix-{}-{}-{}",
+                name,
+                std::process::id(),
+                NEXT_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl std::ops::Deref for TestDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TestDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl From<&TestDir> for PathBuf {
+        fn from(dir: &TestDir) -> Self {
+            dir.0.clone()
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
 
     #[test]
     fn test_workload_type_detection() {
@@ -21,12 +71,137 @@ mod tests {
         assert_eq!(WorkloadType::from_path("unknown.txt"), None);
     }
 
+    #[test]
+    fn test_workload_type_detection_by_content() {
+        let root = TestDir::new("detect");
+
+        // A 32-bit ELF header followed by one executable PT_LOAD mapping the entry point
+        let elf = |class: u8, elf_type: u16, machine: u16| {
+            let mut elf = vec![0u8; 52 + 32];
+            elf[..4].copy_from_slice(b"\x7fELF");
+            elf[4] = class;
+            elf[5] = 1;
+            elf[6] = 1;
+            elf[16..18].copy_from_slice(&elf_type.to_le_bytes());
+            elf[18..20].copy_from_slice(&machine.to_le_bytes());
+            elf[24..28].copy_from_slice(&0x0800_0000u32.to_le_bytes());
+            elf[28..32].copy_from_slice(&52u32.to_le_bytes());
+            elf[42..44].copy_from_slice(&32u16.to_le_bytes());
+            elf[44..46].copy_from_slice(&1u16.to_le_bytes());
+            elf[52..56].copy_from_slice(&1u32.to_le_bytes());
+            elf[60..64].copy_from_slice(&0x0800_0000u32.to_le_bytes());
+            elf[72..76].copy_from_slice(&0x1000u32.to_le_bytes());
+            elf[76..80].copy_from_slice(&5u32.to_le_bytes());
+            elf
+        };
+        let write = |name: &str, content: &[u8]| {
+            let path = root.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+
+        let hello = write("hello", &elf(1, 2, 3));
+        assert_eq!(WorkloadType::from_path(&hello), Some(WorkloadType::Binary));
+        assert_eq!(
+            Runtime::resolve_workload(&hello).unwrap(),
+            WorkloadType::Binary
+        );
+        let tool = write("tool.bin", &elf(1, 2, 3));
+        assert_eq!(WorkloadType::from_path(&tool), Some(WorkloadType::Binary));
+
+        let script = write("run", b"#!/usr/bin/env python3\nprint('hi')\n");
+        assert_eq!(WorkloadType::from_path(&script), Some(WorkloadType::Python));
+        let script = write("run.txt", b"#!/usr/bin/env -S qjs --std\n");
+        assert_eq!(
+            WorkloadType::from_path(&script),
+            Some(WorkloadType::JavaScript)
+        );
+        let notes = write("notes", b"just some text\n");
+        assert_eq!(WorkloadType::from_path(&notes), None);
+        let object = write("empty.o", b"");
+        assert_eq!(WorkloadType::from_path(&object), None);
+
+        // ELF files that would not run in the guest are rejected up front
+        for (name, content) in [
+            ("host", elf(2, 2, 62)),
+            ("arm", elf(1, 2, 40)),
+            ("hello.o", elf(1, 1, 3)),
+        ] {
+            let path = write(name, &content);
+            assert!(
+                matches!(
+                    Runtime::resolve_workload(&path),
+                    Err(Error::InvalidWorkload(_))
+                ),
+                "{}",
+                name
+            );
+        }
+        let message = Runtime::resolve_workload(&root.join("host"))
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("64-bit x86-64"), "{}", message);
+
+        // Program header counts beyond the end of the file are refused before allocating
+        let mut oversized = elf(1, 2, 3);
+        oversized[42..44].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        oversized[44..46].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        let oversized = write("oversized", &oversized);
+        let message = Runtime::resolve_workload(&oversized)
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("truncated program headers"), "{}", message);
+    }
+
+    #[test]
+    fn test_workload_type_from_str() {
+        assert_eq!("javascript".parse().ok(), Some(WorkloadType::JavaScript));
+        assert_eq!("JS".parse().ok(), Some(WorkloadType::JavaScript));
+        assert_eq!("python".parse().ok(), Some(WorkloadType::Python));
+        assert!(matches!(
+            "ruby".parse::<WorkloadType>(),
+            Err(Error::InvalidWorkload(_))
+        ));
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(Error::KernelMissing.error_code(), "KERNEL_MISSING");
+        assert_eq!(Error::GuestExit(3).error_code(), "GUEST_EXIT");
+        assert_eq!(
+            Error::GuestExit(3).to_string(),
+            "Guest exited with status 3"
+        );
+    }
+
     #[test]
     fn test_workload_type_binary_names() {
         assert_eq!(WorkloadType::JavaScript.binary_name(), "qjs");
         assert_eq!(WorkloadType::Python.binary_name(), "python3");
     }
 
+    #[test]
+    fn test_exit_status() {
+        assert!(ExitStatus::from_code(0).success());
+        assert!(!ExitStatus::from_code(3).success());
+        assert_eq!(ExitStatus::from_code(3).code(), Some(3));
+        assert!(ExitStatus::unknown().success());
+        assert_eq!(ExitStatus::unknown().code(), None);
+    }
+
+    #[test]
+    fn test_quote_arg() {
+        use crate::runtime::quote_arg;
+
+        assert_eq!(quote_arg("--foo"), "--foo");
+        assert_eq!(quote_arg("/tmp/script.py"), "/tmp/script.py");
+        assert_eq!(quote_arg("two words"), "'two words'");
+        assert_eq!(quote_arg(""), "''");
+        assert_eq!(quote_arg("it's"), "'it'\\''s'");
+        assert_eq!(quote_arg("say \"hi\""), "'say \"hi\"'");
+        assert_eq!(quote_arg("$HOME *"), "'$HOME *'");
+    }
+
     #[test]
     fn test_runtime_config_builder() {
         let config = RuntimeConfig::new()
@@ -36,6 +211,1012 @@ mod tests {
         assert_eq!(config.log_directory, "/custom/log");
         assert_eq!(config.tmp_directory, "/custom/tmp");
         assert!(config.syscall_table.is_none());
+        assert!(config.timeout.is_none());
+    }
+
+    #[test]
+    fn test_runtime_config_with_offline() {
+        assert!(RuntimeConfig::new().with_offline(true).offline);
+        assert!(!RuntimeConfig::new().with_offline(false).offline);
+    }
+
+    #[tokio::test]
+    async fn test_offline_run_without_cache_reports_not_cached() {
+        let cache_dir = TestDir::new("offline");
+        let config = RuntimeConfig::new()
+            .with_cache_directory(cache_dir.to_string_lossy())
+            .with_offline(true);
+        let mut sandbox = Sandbox::new(config).unwrap();
+
+        let error = sandbox.run("guest-examples/hello.js").await.unwrap_err();
+        assert!(matches!(error, Error::NotCached(_)), "{}", error);
+        assert_eq!(error.error_code(), "NOT_CACHED");
+    }
+
+    #[test]
+    fn test_env_flag_enabled() {
+        use crate::runtime::env_flag_enabled;
+
+        assert!(env_flag_enabled("1"));
+        assert!(env_flag_enabled("TRUE"));
+        assert!(env_flag_enabled(" yes "));
+        assert!(!env_flag_enabled("0"));
+        assert!(!env_flag_enabled(""));
+    }
+
+    #[test]
+    fn test_runtime_config_with_timeout() {
+        let config = RuntimeConfig::new().with_timeout(Duration::from_secs(5));
+        assert_eq!(config.timeout, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_runtime_config_with_resource_limits() {
+        let config = RuntimeConfig::new()
+            .with_process_memory_limit(64 << 20)
+            .with_process_cpu_time_limit(Duration::from_secs(2))
+            .with_output_limit(4096);
+        assert_eq!(config.limits.process_memory, Some(64 << 20));
+        assert_eq!(config.limits.process_cpu_time, Some(Duration::from_secs(2)));
+        assert_eq!(config.limits.output, Some(4096));
+        assert!(RuntimeConfig::new().limits.is_unlimited());
+        assert!(config.limits.measures_process());
+        assert!(!RuntimeConfig::new()
+            .with_output_limit(4096)
+            .limits
+            .measures_process());
+
+        assert_eq!(
+            Error::LimitExceeded(Limit::Output(4096)).error_code(),
+            "OUTPUT_LIMIT_EXCEEDED"
+        );
+        assert_eq!(
+            Error::LimitExceeded(Limit::ProcessMemory(1024)).to_string(),
+            "Workload exceeded its host process memory limit of 1024 bytes"
+        );
+        assert_eq!(
+            Error::LimitExceeded(Limit::ProcessCpuTime(Duration::from_secs(1))).error_code(),
+            "PROCESS_CPU_TIME_LIMIT_EXCEEDED"
+        );
+    }
+
+    #[test]
+    fn test_output_limit_counts_output_of_current_run() {
+        use crate::limits::ResourceMonitor;
+
+        let dir = TestDir::new("console");
+        let console = dir.join("guest-console.log");
+        std::fs::write(&console, b"output of a previous run\n").unwrap();
+        let offset = std::fs::metadata(&console).unwrap().len();
+
+        let limits = ResourceLimits {
+            output: Some(8),
+            ..ResourceLimits::default()
+        };
+        let monitor = ResourceMonitor::start(limits, &console, offset);
+        assert_eq!(monitor.check(), None);
+
+        std::fs::write(&console, b"output of a previous run\n0123456789").unwrap();
+        assert_eq!(monitor.check(), Some(Limit::Output(8)));
+    }
+
+    #[test]
+    fn test_runtime_config_with_cache_directory() {
+        let config = RuntimeConfig::new().with_cache_directory("/opt/nanvix-cache");
+        assert_eq!(config.cache_directory.as_deref(), Some("/opt/nanvix-cache"));
+    }
+
+    #[test]
+    fn test_cache_probes_custom_directory() {
+        use crate::cache::Cache;
+
+        let root = TestDir::new("cache");
+        let bin_dir = root.join("hyperlight-single-process-test").join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        std::fs::write(bin_dir.join("qjs"), b"").unwrap();
+
+        let cache = Cache::new(&root);
+        assert_eq!(
+            cache.registry_root(),
+            Some(root.to_string_lossy().into_owned())
+        );
+        // The registry keeps its own default location unless a root is chosen
+        assert_eq!(Cache::default().registry_root(), None);
+        assert!(cache.is_binary_cached("qjs"));
+        assert!(!cache.is_binary_cached("python3"));
+        assert_eq!(
+            cache.find_cached_binary_path("qjs"),
+            Some(bin_dir.join("qjs").to_string_lossy().into_owned())
+        );
+    }
+
+    #[test]
+    fn test_flavor_from_str() {
+        let flavor: Flavor = "hyperlight/single-process".parse().unwrap();
+        assert_eq!(flavor.machine(), "hyperlight");
+        assert_eq!(flavor.deployment(), "single-process");
+        assert_eq!(flavor, Flavor::default());
+        assert_eq!(Flavor::default().to_string(), "hyperlight/single-process");
+        for invalid in [
+            "hyperlight",
+            "hyperlight/single",
+            "hyper-light/single-process",
+        ] {
+            assert!(
+                matches!(invalid.parse::<Flavor>(), Err(Error::InvalidFlavor(_))),
+                "{}",
+                invalid
+            );
+        }
+
+        // Other deployment types known to the registry can be selected
+        let multi: Flavor = "hyperlight/multi-process".parse().unwrap();
+        assert_eq!(multi, Flavor::new("hyperlight", "multi-process"));
+    }
+
+    #[tokio::test]
+    async fn test_run_with_unsupported_flavor_fails() {
+        let cache_dir = TestDir::new("unsupported-flavor");
+        let config = RuntimeConfig::new()
+            .with_cache_directory(cache_dir.to_string_lossy())
+            .with_offline(true)
+            .with_flavor(Flavor::new("hyperlight", "standalone"));
+        let mut sandbox = Sandbox::new(config).unwrap();
+
+        // The sandbox is created, but no guest can run with the flavor
+        let error = sandbox.run("guest-examples/hello.js").await.unwrap_err();
+        assert!(matches!(error, Error::UnsupportedFlavor(_)), "{}", error);
+        assert_eq!(error.error_code(), "UNSUPPORTED_FLAVOR");
+    }
+
+    #[test]
+    fn test_cache_is_keyed_on_flavor() {
+        use crate::cache::Cache;
+
+        let root = TestDir::new("flavor");
+        let bin_dir = root.join("hyperlight-multi-process-test").join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        std::fs::write(bin_dir.join("qjs"), b"").unwrap();
+
+        let single = Cache::new(&root);
+        let multi = Cache::new(&root).with_flavor(Flavor::new("hyperlight", "multi-process"));
+        assert!(!single.is_binary_cached("qjs"));
+        assert!(multi.is_binary_cached("qjs"));
+
+        // A deployment name that prefixes another must not claim its releases
+        let bin_dir = root.join("hyperlight-single-process-test").join("bin");
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        std::fs::write(bin_dir.join("python3"), b"").unwrap();
+        let prefix = Cache::new(&root).with_flavor(Flavor::new("hyperlight", "single"));
+        assert!(!prefix.is_binary_cached("python3"));
+        assert!(single.is_binary_cached("python3"));
+    }
+
+    #[test]
+    fn test_version_req_matches() {
+        use crate::cache::VersionReq;
+
+        let req: VersionReq = "3.12.x".parse().unwrap();
+        assert!(req.matches("3.12.1"));
+        assert!(req.matches("python-v3.12.9"));
+        assert!(!req.matches("3.13.0"));
+        assert!(!req.matches("nightly"));
+        assert!("3.12".parse::<VersionReq>().unwrap().matches("3.12.4"));
+        assert!("*".parse::<VersionReq>().unwrap().matches("0.1.0"));
+        assert!(matches!(
+            "3.twelve".parse::<VersionReq>(),
+            Err(Error::InvalidVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_cache_selects_releases_deterministically() {
+        use crate::cache::{Cache, Release};
+
+        let root = TestDir::new("releases");
+        for release in ["3.11.2", "3.12.1", "3.12.10"] {
+            let bin_dir = root
+                .join(format!("hyperlight-single-process-{}", release))
+                .join("bin");
+            std::fs::create_dir_all(&bin_dir).unwrap();
+            std::fs::write(bin_dir.join("python3"), release).unwrap();
+        }
+
+        let cache = Cache::new(&root);
+        let latest = cache.find_release("python3", &Release::Latest).unwrap();
+        assert_eq!(latest.release, "3.12.10");
+        let pinned = cache
+            .find_release("python3", &Release::Matching("3.11.x".parse().unwrap()))
+            .unwrap();
+        assert_eq!(pinned.release, "3.11.2");
+        let exact = cache
+            .find_release("python3", &Release::Exact("3.12.1".to_string()))
+            .unwrap();
+        assert_eq!(exact.release, "3.12.1");
+        assert!(cache
+            .find_release("python3", &Release::Matching("3.13".parse().unwrap()))
+            .is_none());
+
+        // Kernel and interpreter are only taken together from one release
+        let kernel_dir = root.join("hyperlight-single-process-3.12.1").join("bin");
+        std::fs::write(kernel_dir.join("kernel.elf"), b"").unwrap();
+        let kernel_dir = root.join("hyperlight-single-process-3.13.0").join("bin");
+        std::fs::create_dir_all(&kernel_dir).unwrap();
+        std::fs::write(kernel_dir.join("kernel.elf"), b"").unwrap();
+        let unit = cache
+            .find_release_providing(&["kernel.elf", "python3"], &Release::Latest)
+            .unwrap();
+        assert_eq!(unit.len(), 2);
+        assert!(unit.iter().all(|binary| binary.release == "3.12.1"));
+        assert!(unit[1].path.ends_with("python3"));
+        assert!(cache
+            .find_release_providing(
+                &["kernel.elf", "python3"],
+                &Release::Matching("3.13".parse().unwrap())
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_lockfile_round_trip() {
+        use crate::lockfile::{LockedPackage, Lockfile};
+
+        let dir = TestDir::new("lockfile");
+        let path = dir.join("hyperlight-nanvix.lock");
+
+        let mut lockfile = Lockfile::open(&path).unwrap();
+        assert!(lockfile.packages().is_empty());
+        lockfile
+            .record(LockedPackage {
+                name: "python".to_string(),
+                binary: "python3".to_string(),
+                flavor: "hyperlight/single-process".to_string(),
+                release: "3.12.1".to_string(),
+                sha256: "00ff".to_string(),
+            })
+            .unwrap();
+
+        let reloaded = Lockfile::open(&path).unwrap();
+        assert_eq!(reloaded, lockfile);
+        let locked = reloaded
+            .find("hyperlight/single-process", "python3")
+            .unwrap();
+        assert_eq!(locked.release, "3.12.1");
+        assert!(reloaded
+            .find("hyperlight/multi-process", "python3")
+            .is_none());
+    }
+
+    #[test]
+    fn test_cache_list_prune_and_remove() {
+        use crate::cache::Cache;
+        use crate::integrity::DigestStore;
+
+        let root = TestDir::new("manage");
+        for name in [
+            "hyperlight-single-process-0.1.0",
+            "hyperlight-single-process-0.2.0",
+            "hyperlight-multi-process-0.1.0",
+            "scratch",
+        ] {
+            let bin_dir = root.join(name).join("bin");
+            std::fs::create_dir_all(&bin_dir).unwrap();
+            std::fs::write(bin_dir.join("qjs"), b"qjs").unwrap();
+        }
+        let cache = Cache::new(&root).with_digest_store(DigestStore::new(root.join("digests")));
+
+        let releases = cache.list_releases().unwrap();
+        assert_eq!(releases.len(), 4);
+        let multi = &releases[0];
+        assert_eq!(multi.name, "hyperlight-multi-process-0.1.0");
+        assert_eq!(
+            multi.flavor,
+            Some(Flavor::new("hyperlight", "multi-process"))
+        );
+        assert_eq!(multi.release, "0.1.0");
+        assert_eq!(multi.binaries, vec!["qjs".to_string()]);
+        assert_eq!(multi.size, 3);
+        assert_eq!(releases[3].flavor, None);
+
+        let removed = cache.prune(1).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].name, "hyperlight-single-process-0.1.0");
+
+        assert!(matches!(
+            cache.remove_release("../scratch"),
+            Err(Error::ReleaseNotFound(_))
+        ));
+        cache.remove_release("scratch").unwrap();
+        assert_eq!(cache.list_releases().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_cache_export_and_import_release() {
+        use crate::cache::Cache;
+        use crate::integrity::DigestStore;
+
+        let base = TestDir::new("archive");
+        let release = base.join("source").join("hyperlight-single-process-0.3.0");
+        std::fs::create_dir_all(release.join("bin")).unwrap();
+        std::fs::create_dir_all(release.join("lib")).unwrap();
+        std::fs::write(release.join("bin").join("kernel.elf"), b"kernel").unwrap();
+        std::fs::write(release.join("bin").join("qjs"), b"qjs").unwrap();
+        std::fs::write(release.join("lib").join("libqjs.so.1"), b"lib").unwrap();
+        // Links within the release survive the round trip
+        std::os::unix::fs::symlink("libqjs.so.1", release.join("lib").join("libqjs.so")).unwrap();
+        let digests = DigestStore::new(base.join("digests"));
+
+        let source = Cache::new(base.join("source")).with_digest_store(digests.clone());
+        let archive = source
+            .export_release("hyperlight-single-process-0.3.0", base.join("out"))
+            .unwrap();
+
+        let target = Cache::new(base.join("target")).with_digest_store(digests.clone());
+        let info = target.import_release(&archive).unwrap();
+        assert_eq!(info.name, "hyperlight-single-process-0.3.0");
+        assert_eq!(
+            info.binaries,
+            vec!["kernel.elf".to_string(), "qjs".to_string()]
+        );
+        assert!(target.is_binary_cached("kernel.elf"));
+        assert_eq!(
+            std::fs::read_link(info.path.join("lib").join("libqjs.so")).unwrap(),
+            std::path::Path::new("libqjs.so.1")
+        );
+        // Importing records digests, unlike dropping binaries into the cache
+        let imported = target
+            .find_release("kernel.elf", &crate::cache::Release::Latest)
+            .unwrap();
+        assert!(digests.verify(&imported.path).is_ok());
+        let source_kernel = source
+            .find_release("kernel.elf", &crate::cache::Release::Latest)
+            .unwrap();
+        assert!(matches!(
+            digests.verify(&source_kernel.path),
+            Err(Error::DigestMissing(_))
+        ));
+        assert!(matches!(
+            target.import_release(&archive),
+            Err(Error::ReleaseExists(_))
+        ));
+
+        // Links leading outside the release are neither exported nor imported
+        std::os::unix::fs::symlink("../../../digests", release.join("lib").join("escape")).unwrap();
+        assert!(matches!(
+            source.export_release("hyperlight-single-process-0.3.0", base.join("out")),
+            Err(Error::InvalidArchive { .. })
+        ));
+        std::fs::remove_file(release.join("lib").join("escape")).unwrap();
+
+        // Releases without a kernel, an interpreter or a lib/ directory are rejected
+        let empty = Cache::new(base.join("empty"));
+        for missing in ["bin/qjs", "bin/kernel.elf", "lib"] {
+            let path = release.join(missing);
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path).unwrap();
+            } else {
+                std::fs::remove_file(&path).unwrap();
+            }
+            let archive = source
+                .export_release("hyperlight-single-process-0.3.0", base.join("out"))
+                .unwrap();
+            assert!(
+                matches!(
+                    empty.import_release(&archive),
+                    Err(Error::InvalidArchive { .. })
+                ),
+                "{}",
+                missing
+            );
+            std::fs::create_dir_all(release.join("lib")).unwrap();
+            std::fs::write(release.join("bin").join("kernel.elf"), b"kernel").unwrap();
+            std::fs::write(release.join("bin").join("qjs"), b"qjs").unwrap();
+        }
+        assert!(empty.list_releases().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_digest_store_detects_tampering() {
+        use crate::integrity::DigestStore;
+
+        let dir = TestDir::new("digests");
+        let binary = dir.join("qjs");
+        std::fs::write(&binary, b"original").unwrap();
+
+        let store = DigestStore::new(dir.join("digests.toml"));
+        // Strict verification, as used for snapshots, refuses unrecorded files
+        assert!(matches!(
+            store.verify(&binary),
+            Err(Error::DigestMissing(_))
+        ));
+        let recorded = store.record(&binary).unwrap();
+        assert_eq!(store.verify(&binary).unwrap(), recorded);
+
+        std::fs::write(&binary, b"orig").unwrap();
+        assert!(matches!(
+            store.verify(&binary),
+            Err(Error::IntegrityMismatch { .. })
+        ));
+        // Installing again keeps the digest recorded at the first install
+        store.record_new(&binary).unwrap();
+        assert!(matches!(
+            store.verify(&binary),
+            Err(Error::IntegrityMismatch { .. })
+        ));
+
+        // Forgetting the digests accepts the re-fetched binary once recorded
+        store.forget_under(&dir).unwrap();
+        store.record_new(&binary).unwrap();
+        assert!(store.verify(&binary).is_ok());
+    }
+
+    #[test]
+    fn test_cache_refuses_unrecorded_binaries_until_trusted() {
+        use crate::cache::{Cache, Release};
+        use crate::integrity::DigestStore;
+
+        // A cache populated before digests were recorded, or by hand
+        let dir = TestDir::new("pre-existing-cache");
+        let bin = dir.join("cache/hyperlight-single-process-0.4.0/bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("kernel.elf"), b"kernel").unwrap();
+        let digests = DigestStore::new(dir.join("digests.toml"));
+        let cache = Cache::new(dir.join("cache")).with_digest_store(digests.clone());
+
+        let kernel = cache.find_release("kernel.elf", &Release::Latest).unwrap();
+        assert!(matches!(
+            cache.verify(&kernel),
+            Err(Error::DigestMissing(_))
+        ));
+
+        // Trusting the cache records its binaries once, later uses are checked
+        assert_eq!(cache.trust_existing().unwrap(), vec![kernel.path.clone()]);
+        let recorded = cache.verify(&kernel).unwrap();
+        assert_eq!(digests.verify(&kernel.path).unwrap(), recorded);
+        assert!(cache.trust_existing().unwrap().is_empty());
+
+        // A newer release planted in the shared cache is selected but refused
+        let planted = dir.join("cache/hyperlight-single-process-999.0.0/bin");
+        std::fs::create_dir_all(&planted).unwrap();
+        std::fs::write(planted.join("kernel.elf"), b"planted").unwrap();
+        let newest = cache.find_release("kernel.elf", &Release::Latest).unwrap();
+        assert_eq!(newest.release, "999.0.0");
+        assert!(matches!(
+            cache.verify(&newest),
+            Err(Error::DigestMissing(_))
+        ));
+
+        // Trusting again never accepts a binary modified since it was recorded
+        std::fs::write(&kernel.path, b"tampered").unwrap();
+        assert!(matches!(
+            cache.verify(&kernel),
+            Err(Error::IntegrityMismatch { .. })
+        ));
+        assert!(matches!(
+            cache.trust_existing(),
+            Err(Error::IntegrityMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_only_cache_carries_its_digests() {
+        use crate::cache::{Cache, Release};
+        use crate::integrity::DigestStore;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TestDir::new("read-only-cache");
+        let bin = dir.join("cache/hyperlight-single-process-0.4.0/bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("kernel.elf"), b"kernel").unwrap();
+        std::fs::write(bin.join("qjs"), b"qjs").unwrap();
+
+        // The image ships its digests next to the cache, recorded when it was built
+        let shipped = DigestStore::new(dir.join("cache-digests.toml"));
+        shipped.record(&bin.join("kernel.elf")).unwrap();
+        shipped.record(&bin.join("qjs")).unwrap();
+        let shipped_contents = std::fs::read(shipped.path()).unwrap();
+
+        let set_mode = |path: &Path, mode: u32| {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap()
+        };
+        let release = bin.parent().unwrap();
+        for path in [bin.as_path(), release] {
+            set_mode(path, 0o555);
+        }
+        set_mode(shipped.path(), 0o444);
+
+        let cache = Cache::new(dir.join("cache")).with_digest_store(shipped.clone());
+        let found = cache
+            .find_release_providing(&["kernel.elf", "qjs"], &Release::Latest)
+            .unwrap();
+        for binary in &found {
+            assert!(cache.verify(binary).is_ok());
+            cache.mark_used(binary);
+        }
+        // Verifying recorded binaries never writes to the digest file
+        assert_eq!(std::fs::read(shipped.path()).unwrap(), shipped_contents);
+
+        // Without shipped digests, the read-only cache is trusted explicitly
+        // into a writable per-user store
+        let per_user = DigestStore::new(dir.join("per-user/cache-digests.toml"));
+        let cache = Cache::new(dir.join("cache")).with_digest_store(per_user.clone());
+        for binary in &found {
+            assert!(matches!(cache.verify(binary), Err(Error::DigestMissing(_))));
+        }
+        assert_eq!(cache.trust_existing().unwrap().len(), found.len());
+        for binary in &found {
+            assert!(cache.verify(binary).is_ok());
+        }
+
+        for path in [bin.as_path(), release] {
+            set_mode(path, 0o755);
+        }
+        set_mode(shipped.path(), 0o644);
+    }
+
+    #[test]
+    fn test_snapshots_are_keyed_on_binary_digests() {
+        use crate::integrity::DigestStore;
+        use crate::snapshot::{SnapshotKey, SnapshotStore};
+
+        let dir = TestDir::new("snapshots");
+        let store =
+            SnapshotStore::new(&dir).with_digest_store(DigestStore::new(dir.join("digests.toml")));
+
+        let flavor = Flavor::default();
+        let old = SnapshotKey::new(WorkloadType::Python, flavor.clone(), "k1", "p1");
+        let new = SnapshotKey::new(WorkloadType::Python, flavor.clone(), "k1", "p2");
+        let js = SnapshotKey::new(WorkloadType::JavaScript, flavor, "k1", "q1");
+        assert_ne!(old.file_name(), new.file_name());
+        assert!(old
+            .file_name()
+            .starts_with("python3-hyperlight-single-process-"));
+
+        // A guest that never wrote its snapshot fails the commit
+        let staged = store.stage(&old).unwrap();
+        assert!(matches!(
+            store.commit(&old, staged),
+            Err(Error::SnapshotFailed(_))
+        ));
+        assert_eq!(store.find(&old).unwrap(), None);
+
+        for key in [&old, &js] {
+            let staged = store.stage(key).unwrap();
+            std::fs::write(staged.path(), b"snapshot").unwrap();
+            store.commit(key, staged).unwrap();
+        }
+        assert_eq!(store.find(&old).unwrap(), Some(store.path(&old)));
+
+        // A snapshot taken with a changed interpreter replaces the stale one
+        let staged = store.stage(&new).unwrap();
+        std::fs::write(staged.path(), b"snapshot").unwrap();
+        store.commit(&new, staged).unwrap();
+        assert_eq!(store.find(&old).unwrap(), None);
+        assert_eq!(store.list().unwrap().len(), 2);
+
+        // A tampered snapshot is never restored
+        std::fs::write(store.path(&new), b"tampered").unwrap();
+        assert!(matches!(
+            store.find(&new),
+            Err(Error::IntegrityMismatch { .. })
+        ));
+
+        // Neither is a snapshot dropped into the directory by someone else
+        let dropped = SnapshotKey::new(WorkloadType::JavaScript, Flavor::default(), "k2", "q2");
+        std::fs::write(store.path(&dropped), b"snapshot").unwrap();
+        assert_eq!(store.find(&dropped).unwrap(), None);
+        std::fs::remove_file(store.path(&dropped)).unwrap();
+
+        assert_eq!(store.clear().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_syscall_policy_evaluation() {
+        use crate::policy::Access;
+        use std::path::Path;
+
+        let policy = SyscallPolicy::from_toml_str(
+            r#"
+            default = "EPERM"
+
+            [[rule]]
+            syscall = "openat"
+            path = "/data"
+            access = "read-only"
+            action = "allow"
+
+            [[rule]]
+            syscall = "openat"
+            path = "/secrets"
+            action = "ENOENT"
+            "#,
+        )
+        .unwrap();
+
+        let openat = |path: &str, access| policy.evaluate("openat", Some(Path::new(path)), access);
+        assert_eq!(
+            openat("/data/input.csv", Access::ReadOnly),
+            PolicyAction::Allow
+        );
+        assert_eq!(
+            openat("/data/input.csv", Access::ReadWrite).errno(),
+            Some(libc::EPERM)
+        );
+        assert_eq!(
+            openat("/data/../etc/passwd", Access::ReadOnly),
+            PolicyAction::Errno(libc::EPERM)
+        );
+        assert_eq!(
+            openat("/secrets/key", Access::ReadOnly),
+            PolicyAction::Errno(libc::ENOENT)
+        );
+        assert_eq!(
+            Access::from_open_flags(libc::O_RDONLY | libc::O_CREAT),
+            Access::ReadWrite
+        );
+
+        // The same policy built in Rust and loaded from JSON
+        let built = SyscallPolicy::new(PolicyAction::Errno(libc::EPERM))
+            .with_rule(PolicyRule::allow("openat").under("/data").read_only())
+            .unwrap()
+            .with_rule(
+                PolicyRule::new("openat", PolicyAction::Errno(libc::ENOENT)).under("/secrets"),
+            )
+            .unwrap();
+        assert_eq!(built, policy);
+        let json = SyscallPolicy::from_json_str(
+            r#"{"default": "EPERM", "rule": [
+                {"syscall": "openat", "path": "/data", "access": "read-only", "action": "allow"},
+                {"syscall": "openat", "path": "/secrets", "action": "ENOENT"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(json, policy);
+
+        match SyscallPolicy::new(PolicyAction::Allow).with_rule(PolicyRule::deny("unlink")) {
+            Err(Error::InvalidPolicy(message)) => assert!(message.contains("only exposes: openat")),
+            other => panic!("unlink rule accepted: {:?}", other),
+        }
+        assert!(matches!(
+            SyscallPolicy::from_toml_str("default = \"sometimes\""),
+            Err(Error::InvalidPolicy(_))
+        ));
+
+        // Rules pushed to the public fields are checked when the runtime is created
+        let mut pushed = SyscallPolicy::new(PolicyAction::Allow);
+        pushed.rules.push(PolicyRule::deny("unlink"));
+        assert!(matches!(
+            Runtime::new(RuntimeConfig::new().with_syscall_policy(pushed)),
+            Err(Error::InvalidPolicy(_))
+        ));
+    }
+
+    #[test]
+    fn test_canonical_path_follows_links_before_parent_dirs() {
+        use crate::policy::canonical_or_normalized;
+
+        let root = TestDir::new("canonical-path");
+        let allowed = root.join("allowed");
+        let inner = root.join("private/inner");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::create_dir_all(&inner).unwrap();
+        std::fs::write(root.join("private/secret"), b"secret").unwrap();
+        std::os::unix::fs::symlink(&inner, allowed.join("link")).unwrap();
+        let private = root.join("private").canonicalize().unwrap();
+
+        // `..` after a link leaves the link's target, as it does in the kernel,
+        // rather than the directory the link is in
+        assert_eq!(
+            canonical_or_normalized(&allowed.join("link/../secret")),
+            private.join("secret")
+        );
+        assert_eq!(
+            canonical_or_normalized(&allowed.join("link/../missing.txt")),
+            private.join("missing.txt")
+        );
+        assert_eq!(
+            canonical_or_normalized(&allowed.join("missing/../new.txt")),
+            allowed.canonicalize().unwrap().join("new.txt")
+        );
+    }
+
+    #[test]
+    fn test_guest_syscalls_confine_openat() {
+        use crate::syscalls::{GuestSyscalls, RunState};
+        use std::ffi::CString;
+
+        let root = TestDir::new("guest-syscalls");
+        let release = root.join("cache/qjs/1.0");
+        let sources = root.join("tmp/sources");
+        std::fs::create_dir_all(release.join("bin")).unwrap();
+        std::fs::create_dir_all(release.join("lib")).unwrap();
+        std::fs::create_dir_all(&sources).unwrap();
+        let interpreter = release.join("bin/qjs");
+        let stdlib = release.join("lib/std.js");
+        let kernel = release.join("bin/kernel.elf");
+        let workload = sources.join("script.js");
+        let other_source = sources.join("other.js");
+        let console = root.join("tmp/guest-console.log");
+        for path in [
+            &interpreter,
+            &stdlib,
+            &kernel,
+            &workload,
+            &other_source,
+            &console,
+        ] {
+            std::fs::write(path, b"content").unwrap();
+        }
+
+        let run = RunState::default();
+        let table = GuestSyscalls::new(
+            None,
+            Some(&SyscallPolicy::new(PolicyAction::Errno(libc::EACCES))),
+            &[interpreter.clone(), release.join("lib")],
+            run.clone(),
+        )
+        .into_table();
+        let SyscallAction::Forward(openat) = &table.openat else {
+            panic!("openat is not intercepted");
+        };
+        let open = |path: &Path, flags: i32| {
+            let path = CString::new(path.as_os_str().as_encoded_bytes()).unwrap();
+            // SAFETY: the state is the one the table was built with and the path is NUL-terminated
+            let fd = unsafe { openat(&table.state, libc::AT_FDCWD, path.as_ptr(), flags, 0o644) };
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error().raw_os_error());
+            }
+            // SAFETY: fd was just opened and is not used elsewhere
+            unsafe { libc::close(fd) };
+            Ok(())
+        };
+        run.begin(&workload, None, None, None);
+
+        // The workload, the interpreter and its standard library stay readable
+        assert_eq!(open(&workload, libc::O_RDONLY), Ok(()));
+        assert_eq!(open(&interpreter, libc::O_RDONLY), Ok(()));
+        assert_eq!(open(&stdlib, libc::O_RDONLY), Ok(()));
+
+        // Nothing else in the cache or the temporary directory does, nor are
+        // the exempt files writable
+        let denied = Err(Some(libc::EACCES));
+        assert_eq!(open(&stdlib, libc::O_WRONLY), denied);
+        assert_eq!(open(&kernel, libc::O_RDONLY), denied);
+        assert_eq!(open(&other_source, libc::O_RDONLY), denied);
+        assert_eq!(open(&console, libc::O_RDONLY), denied);
+
+        // Once the run ends its workload is confined like any other file
+        run.end();
+        assert_eq!(open(&workload, libc::O_RDONLY), denied);
+    }
+
+    #[test]
+    fn test_syscall_audit_records() {
+        use crate::audit::openat_args;
+        use std::path::Path;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let sink = JsonLinesSink::new(Vec::new());
+        let args = openat_args(
+            libc::AT_FDCWD,
+            Path::new("/tmp/test_file.txt"),
+            libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+            0o644,
+        );
+        sink.record(
+            &SyscallEvent::new(UNIX_EPOCH, "openat", args, -1, Some(libc::EACCES))
+                .with_host_path(Some(Path::new("/srv/scratch/test_file.txt"))),
+        );
+        sink.record(&SyscallEvent::new(
+            SystemTime::now(),
+            "openat",
+            openat_args(3, Path::new("input.csv"), libc::O_RDONLY, 0),
+            4,
+            None,
+        ));
+
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let events: Vec<SyscallEvent> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].args["dirfd"], "AT_FDCWD");
+        assert_eq!(events[0].args["pathname"], "/tmp/test_file.txt");
+        assert_eq!(events[0].args["flags"], "O_WRONLY|O_CREAT|O_TRUNC");
+        assert_eq!(events[0].error.as_deref(), Some("EACCES"));
+        assert_eq!(
+            events[0].host_path.as_deref(),
+            Some("/srv/scratch/test_file.txt")
+        );
+        assert_eq!(events[1].args["dirfd"], 3);
+        assert_eq!(events[1].args["flags"], "O_RDONLY");
+        assert_eq!(events[1].result, 4);
+        assert!(!output.lines().nth(1).unwrap().contains("errno"));
+    }
+
+    #[test]
+    fn test_syscall_hooks_share_typed_state() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let opened = Arc::new(AtomicUsize::new(0));
+        let hooks =
+            SyscallHooks::with_shared_state(opened.clone()).on_openat(|ctx, path, flags, mode| {
+                if path.to_bytes().starts_with(b"/etc") {
+                    return Err(libc::EACCES);
+                }
+                if path.to_bytes() == b"/boom" {
+                    panic!("hook failure");
+                }
+                ctx.state().fetch_add(1, Ordering::Relaxed);
+                ctx.openat(path, flags, mode)
+            });
+        assert!(hooks.handles("openat"));
+        assert!(!SyscallHooks::new().handles("openat"));
+
+        let next = |dirfd, _: &std::ffi::CStr, _, _| Ok(dirfd + 10);
+        let openat = |path: &std::ffi::CStr| hooks.openat(3, path, libc::O_RDONLY, 0, &next);
+        assert_eq!(openat(c"/data/input.csv"), Ok(13));
+        assert_eq!(openat(c"/etc/passwd"), Err(libc::EACCES));
+        assert_eq!(openat(c"/boom"), Err(libc::EIO));
+        assert_eq!(opened.load(Ordering::Relaxed), 1);
+
+        let config = RuntimeConfig::new().with_syscall_hooks(hooks);
+        assert!(config.syscall_hooks.is_some());
+    }
+
+    #[test]
+    fn test_syscall_trace_round_trip_and_replay() {
+        use crate::audit::openat_args;
+        use crate::trace::{Replayed, TraceHeader, TraceReplay, TraceWriter};
+        use std::path::{Path, PathBuf};
+        use std::time::UNIX_EPOCH;
+
+        let dir = TestDir::new("trace");
+        let path = dir.join("syscall-trace.bin");
+        let header = TraceHeader {
+            workload_path: PathBuf::from("/work/file_ops.js"),
+            args: vec!["--verbose".to_string()],
+            recorded_at_us: 0,
+        };
+        let openat = |pathname: &str, result, errno| {
+            SyscallEvent::new(
+                UNIX_EPOCH,
+                "openat",
+                openat_args(libc::AT_FDCWD, Path::new(pathname), libc::O_RDONLY, 0),
+                result,
+                errno,
+            )
+        };
+        let mut writer = TraceWriter::create(path.clone(), &header, b"console.log(1)").unwrap();
+        writer.record(
+            &openat("/work/file_ops.js", 3, None)
+                .with_host_path(Some(Path::new("/work/file_ops.js"))),
+            Some(b"console.log(1)"),
+        );
+        writer.record(&openat("/data/input.csv", 4, None), Some(b"a,b\n"));
+        writer.record(&openat("/missing", -1, Some(libc::ENOENT)), None);
+        writer.record(&openat("/opt/cache/lib.js", 5, None), None);
+        assert_eq!(writer.finish().unwrap(), path);
+
+        let trace = Arc::new(SyscallTrace::load(&path).unwrap());
+        assert_eq!(trace.header, header);
+        assert_eq!(trace.workload, b"console.log(1)");
+        assert_eq!(trace.records.len(), 4);
+
+        // The staged workload stands in for the recorded one
+        let mut replay = TraceReplay::new(trace.clone());
+        assert!(matches!(
+            replay.openat(Path::new("/tmp/replay/file_ops.js"), true),
+            Replayed::Content(b"console.log(1)")
+        ));
+        assert!(matches!(
+            replay.openat(Path::new("/data/input.csv"), false),
+            Replayed::Content(b"a,b\n")
+        ));
+        assert!(matches!(
+            replay.openat(Path::new("/missing"), false),
+            Replayed::Failed(libc::ENOENT)
+        ));
+        assert!(matches!(
+            replay.openat(Path::new("/opt/cache/lib.js"), false),
+            Replayed::Perform
+        ));
+        assert!(replay.finish().is_ok());
+
+        let mut replay = TraceReplay::new(trace);
+        assert!(matches!(
+            replay.openat(Path::new("/etc/passwd"), false),
+            Replayed::Failed(libc::EIO)
+        ));
+        assert!(replay.finish().unwrap_err().contains("/etc/passwd"));
+
+        std::fs::write(&path, b"not a trace").unwrap();
+        assert!(matches!(
+            SyscallTrace::load(&path),
+            Err(Error::InvalidTrace(_))
+        ));
+
+        // Lengths beyond the caps are rejected before anything is allocated
+        let mut oversized_json = b"HLNXTRC1".to_vec();
+        oversized_json.extend_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &oversized_json).unwrap();
+        assert!(matches!(
+            SyscallTrace::load(&path),
+            Err(Error::InvalidTrace(_))
+        ));
+        let json = serde_json::to_vec(&header).unwrap();
+        let mut oversized_content = b"HLNXTRC1".to_vec();
+        oversized_content.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        oversized_content.extend_from_slice(&json);
+        oversized_content.extend_from_slice(&(u64::MAX - 1).to_le_bytes());
+        std::fs::write(&path, &oversized_content).unwrap();
+        assert!(matches!(
+            SyscallTrace::load(&path),
+            Err(Error::InvalidTrace(_))
+        ));
+    }
+
+    #[test]
+    fn test_fault_injection() {
+        use crate::faults::FaultInjector;
+        use std::path::Path;
+
+        let fault: Fault = "openat:ENOENT:0.25".parse().unwrap();
+        assert_eq!(fault.errno, libc::ENOENT);
+        assert_eq!(fault.trigger, FaultTrigger::Probability(0.25));
+        assert_eq!(fault.to_string(), "openat:ENOENT:0.25");
+        for spec in [
+            "read:EIO",
+            "openat:ENOPE",
+            "openat:EIO:1.5",
+            "openat:EIO:every=0",
+        ] {
+            assert!(
+                matches!(spec.parse::<Fault>(), Err(Error::InvalidFault(_))),
+                "{}",
+                spec
+            );
+        }
+
+        let mut injector = FaultInjector::new(
+            &[
+                "openat:EACCES:path=/data/*.csv".parse().unwrap(),
+                "openat:EIO:every=3".parse().unwrap(),
+            ],
+            0,
+        )
+        .unwrap();
+        let mut inject = |path: &str| injector.inject("openat", Some(Path::new(path)));
+        assert_eq!(inject("/data/input.csv"), Some(libc::EACCES));
+        assert_eq!(inject("/data/input.txt"), None);
+        assert_eq!(inject("/etc/hosts"), Some(libc::EIO));
+        assert_eq!(inject("/data/input.csv"), Some(libc::EACCES));
+        assert_eq!(inject("/etc/hosts"), None);
+
+        // The same seed fails the same calls
+        let outcomes = |seed| {
+            let mut injector = FaultInjector::new(std::slice::from_ref(&fault), seed).unwrap();
+            (0..64)
+                .map(|_| injector.inject("openat", None).is_some())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(outcomes(7), outcomes(7));
+        assert_ne!(outcomes(7), outcomes(8));
+        assert!(outcomes(7).contains(&true) && outcomes(7).contains(&false));
+
+        let config =
+            RuntimeConfig::new().with_fault(Fault::new("write", libc::EIO, FaultTrigger::Always));
+        assert!(matches!(Runtime::new(config), Err(Error::InvalidFault(_))));
+    }
+
+    #[test]
+    fn test_runtime_rejects_invalid_release_version() {
+        let config = RuntimeConfig::new().with_release_version("latest-ish");
+        assert!(matches!(
+            Runtime::new(config),
+            Err(Error::InvalidVersion(_))
+        ));
     }
 
     #[test]
@@ -50,6 +1231,102 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_guest_syscalls_chain_to_embedder_table() {
+        use crate::syscalls::{GuestSyscalls, RunState};
+        use std::ffi::CString;
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static LAST: std::sync::Mutex<Option<(PathBuf, i32)>> = std::sync::Mutex::new(None);
+        unsafe fn counting_openat(
+            _state: &(),
+            dirfd: i32,
+            pathname: *const i8,
+            flags: i32,
+            mode: u32,
+        ) -> i32 {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            let path = std::ffi::CStr::from_ptr(pathname).to_str().unwrap();
+            *LAST.lock().unwrap() = Some((PathBuf::from(path), flags));
+            libc::openat(dirfd, pathname, flags, mode)
+        }
+
+        let root = TestDir::new("embedder-table");
+        let allowed = root.join("allowed");
+        let denied = root.join("denied");
+        for path in [&allowed, &denied] {
+            std::fs::write(path, b"content").unwrap();
+        }
+        let link = root.join("link");
+        std::os::unix::fs::symlink(&allowed, &link).unwrap();
+
+        let mut embedder = SyscallTable::new(());
+        embedder.openat = SyscallAction::Forward(counting_openat);
+        let embedder = Arc::new(embedder);
+        let table = GuestSyscalls::new(
+            Some(embedder.clone()),
+            Some(&SyscallPolicy::new(PolicyAction::Errno(libc::EACCES))),
+            &[allowed.clone()],
+            RunState::default(),
+        )
+        .into_table();
+        let SyscallAction::Forward(openat) = &table.openat else {
+            panic!("openat is not intercepted");
+        };
+        let open = |path: &Path| {
+            let path = CString::new(path.as_os_str().as_encoded_bytes()).unwrap();
+            // SAFETY: the state is the one the table was built with and the path is NUL-terminated
+            let fd = unsafe {
+                openat(
+                    &table.state,
+                    libc::AT_FDCWD,
+                    path.as_ptr(),
+                    libc::O_RDONLY,
+                    0,
+                )
+            };
+            if fd >= 0 {
+                // SAFETY: fd was just opened and is not used elsewhere
+                unsafe { libc::close(fd) };
+            }
+            fd >= 0
+        };
+
+        // Syscalls the policy lets through reach the embedder's handler, the
+        // ones it denies never do
+        assert!(open(&allowed));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        assert!(!open(&denied));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        // The handler opens the path that was checked, not the guest's link,
+        // and does not follow a link swapped in since
+        assert!(open(&link));
+        let (path, flags) = LAST.lock().unwrap().clone().unwrap();
+        assert_eq!(path, allowed.canonicalize().unwrap());
+        assert_ne!(flags & libc::O_NOFOLLOW, 0);
+
+        // Forward handlers and the default action can be chained to
+        let config = RuntimeConfig::new().with_syscall_table(embedder);
+        assert!(Runtime::new(config).is_ok());
+        let config = RuntimeConfig::new().with_syscall_table(Arc::new(SyscallTable::new(())));
+        assert!(Runtime::new(config).is_ok());
+    }
+
+    #[test]
+    fn test_pool_config_builder() {
+        let config = PoolConfig::new()
+            .with_size(4)
+            .with_max_reuse(0)
+            .with_idle_timeout(None)
+            .with_workload_types(vec![WorkloadType::Python]);
+
+        assert_eq!(config.size, 4);
+        assert_eq!(config.max_reuse, 1);
+        assert_eq!(config.idle_timeout, None);
+        assert_eq!(config.workload_types, vec![WorkloadType::Python]);
+    }
+
     #[test]
     fn test_sandbox_creation() {
         let config = RuntimeConfig::new();
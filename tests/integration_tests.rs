@@ -1,5 +1,6 @@
-use hyperlight_nanvix::{RuntimeConfig, Sandbox};
+use hyperlight_nanvix::{Error, PoolConfig, RuntimeConfig, Sandbox, SandboxPool, WorkloadType};
 use std::sync::Arc;
+use std::time::Instant;
 
 #[tokio::test]
 async fn test_javascript_hello_world() {
@@ -12,6 +13,216 @@ async fn test_javascript_hello_world() {
         .expect("Failed to run JavaScript");
 }
 
+#[tokio::test]
+async fn test_javascript_captures_output() {
+    let config = RuntimeConfig::new();
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+
+    let output = sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Failed to run JavaScript");
+
+    assert!(output.console.contains("Hello from JavaScript in Nanvix!"));
+    assert!(output.exit_status.success());
+}
+
+#[tokio::test]
+#[ignore = "the pinned nanvix Terminal::run returns () and does not report the guest's exit code"]
+async fn test_guest_exit_code_is_propagated() {
+    let config = RuntimeConfig::new();
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+
+    let output = sandbox
+        .run("guest-examples/exit_code.js")
+        .await
+        .expect("Failed to run JavaScript");
+
+    assert_eq!(output.exit_status.code(), Some(3));
+    assert!(!output.success());
+    assert!(matches!(output.exit_ok(), Err(Error::GuestExit(3))));
+}
+
+#[tokio::test]
+async fn test_arguments_are_passed_to_guest() {
+    let config = RuntimeConfig::new();
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+
+    let output = sandbox
+        .run_with_args("guest-examples/args.js", &["--foo", "two words"])
+        .await
+        .expect("Failed to run JavaScript with arguments");
+
+    assert!(output.console.contains("arg[1]: --foo"));
+    assert!(output.console.contains("arg[2]: two words"));
+}
+
+#[tokio::test]
+async fn test_quoted_arguments_reach_guest_unchanged() {
+    let config = RuntimeConfig::new();
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+
+    let args = ["it's here", "say \"hi\"", "  padded  ", "", "a\\b $HOME *"];
+    let output = sandbox
+        .run_with_args("guest-examples/args.js", &args)
+        .await
+        .expect("Failed to run JavaScript with quoted arguments");
+
+    // Each argument must come out of the guest's argv splitter as one word
+    assert!(
+        output.console.contains("Received 5 argument(s)"),
+        "{}",
+        output.console
+    );
+    for (index, arg) in args.iter().enumerate() {
+        let line = format!("arg[{}]: {}\n", index + 1, arg);
+        assert!(
+            output.console.contains(&line),
+            "missing {:?} in {}",
+            line,
+            output.console
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_timeout_stops_runaway_guest() {
+    use std::time::Duration;
+
+    let config = RuntimeConfig::new().with_timeout(Duration::from_secs(2));
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+
+    let error = sandbox
+        .run("guest-examples/infinite_loop.js")
+        .await
+        .expect_err("Runaway guest should time out");
+
+    assert!(matches!(error, Error::Timeout(_)));
+}
+
+#[tokio::test]
+async fn test_output_limit_stops_guest() {
+    use hyperlight_nanvix::Limit;
+
+    // The process watchdogs are tested in tests/process_limits.rs, where no
+    // other test shares the process they measure
+    let config = RuntimeConfig::new().with_output_limit(16);
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+    let error = sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect_err("Guest output should exceed the limit");
+    assert!(matches!(error, Error::LimitExceeded(Limit::Output(16))));
+}
+
+#[tokio::test]
+async fn test_timeout_covers_wait_for_limited_run() {
+    use std::time::Duration;
+
+    // A memory limit no run can reach still makes limited runs take turns
+    let timeout = Duration::from_secs(5);
+    let config = RuntimeConfig::new()
+        .with_process_memory_limit(1 << 40)
+        .with_timeout(timeout);
+    let pool_config = PoolConfig::new()
+        .with_size(2)
+        .with_workload_types(vec![WorkloadType::JavaScript]);
+    let pool = SandboxPool::new(config, pool_config).expect("Failed to create pool");
+    pool.warm().await.expect("Failed to warm pool");
+
+    // The second run waits for the first, and its timeout runs while it waits
+    let start = Instant::now();
+    let (first, second) = tokio::join!(
+        pool.run("guest-examples/infinite_loop.js"),
+        pool.run("guest-examples/infinite_loop.js"),
+    );
+    assert!(matches!(first, Err(Error::Timeout(_))));
+    assert!(matches!(second, Err(Error::Timeout(_))));
+    assert!(start.elapsed() < timeout * 2, "{:?}", start.elapsed());
+}
+
+#[tokio::test]
+async fn test_cancel_handle_stops_guest() {
+    use std::time::Duration;
+
+    let config = RuntimeConfig::new();
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+    let cancel_handle = sandbox.cancel_handle();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        cancel_handle.cancel();
+    });
+
+    let error = sandbox
+        .run("guest-examples/infinite_loop.js")
+        .await
+        .expect_err("Cancelled guest should fail");
+
+    assert!(matches!(error, Error::Cancelled));
+}
+
+#[tokio::test]
+async fn test_cancel_only_affects_runs_in_progress() {
+    use std::time::Duration;
+
+    let config = RuntimeConfig::new();
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+    let cancel_handle = sandbox.cancel_handle();
+
+    // A cancel with no run in progress must not leak into the next run
+    cancel_handle.cancel();
+    sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Run after an idle cancel should succeed");
+
+    let handle = cancel_handle.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        handle.cancel();
+    });
+    let error = sandbox
+        .run("guest-examples/infinite_loop.js")
+        .await
+        .expect_err("Cancelled guest should fail");
+    assert!(matches!(error, Error::Cancelled));
+
+    // Neither does a cancel that arrives once its run has already finished
+    cancel_handle.cancel();
+    sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Run after a late cancel should succeed");
+}
+
+#[tokio::test]
+async fn test_run_inline_source() {
+    use hyperlight_nanvix::WorkloadType;
+
+    let config = RuntimeConfig::new();
+    let tmp_directory = config.tmp_directory.clone();
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+
+    let output = sandbox
+        .run_source(WorkloadType::Python, "print('inline ' + 'python')")
+        .await
+        .expect("Failed to run inline Python");
+    assert!(output.console.contains("inline python"));
+
+    let output = sandbox
+        .run_source(WorkloadType::JavaScript, "console.log('inline ' + 'js');")
+        .await
+        .expect("Failed to run inline JavaScript");
+    assert!(output.console.contains("inline js"));
+
+    // Staged source files are cleaned up after each run
+    let staged = std::fs::read_dir(format!("{}/sources", tmp_directory))
+        .expect("Sources directory should exist")
+        .count();
+    assert_eq!(staged, 0);
+}
+
 #[tokio::test]
 async fn test_javascript_file_operations() {
     let config = RuntimeConfig::new();
@@ -34,6 +245,189 @@ async fn test_python_hello_world() {
         .expect("Failed to run Python");
 }
 
+#[tokio::test]
+async fn test_python_imports_stdlib_modules() {
+    use hyperlight_nanvix::WorkloadType;
+
+    let mut sandbox = Sandbox::new(RuntimeConfig::new()).expect("Failed to create sandbox");
+
+    // json and textwrap are pure-Python modules, so they are only found when the
+    // interpreter located its stdlib from its cache path
+    let output = sandbox
+        .run_source(
+            WorkloadType::Python,
+            "import json, textwrap\nprint(json.dumps(textwrap.wrap('stdlib found', 6)))",
+        )
+        .await
+        .expect("Failed to import Python stdlib modules")
+        .exit_ok()
+        .expect("Python stdlib import failed");
+
+    assert!(
+        output.console.contains(r#"["stdlib", "found"]"#),
+        "{}",
+        output.console
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_concurrent_python_sandboxes() {
+    let original_dir = std::env::current_dir().expect("Failed to read current directory");
+
+    let mut first = Sandbox::new(RuntimeConfig::new()).expect("Failed to create sandbox");
+    let mut second = Sandbox::new(RuntimeConfig::new()).expect("Failed to create sandbox");
+
+    let (first_output, second_output) = tokio::join!(
+        first.run("guest-examples/hello.py"),
+        second.run("guest-examples/hello.py")
+    );
+
+    assert!(first_output.expect("First Python run failed").success());
+    assert!(second_output.expect("Second Python run failed").success());
+
+    // Python workloads must not touch the host working directory
+    assert_eq!(
+        std::env::current_dir().expect("Failed to read current directory"),
+        original_dir
+    );
+}
+
+#[tokio::test]
+async fn test_sandbox_reuses_guest_across_runs() {
+    let mut sandbox = Sandbox::new(RuntimeConfig::new()).expect("Failed to create sandbox");
+
+    // The first run boots the kernel, the second one skips that
+    let output = sandbox
+        .run_source(
+            WorkloadType::JavaScript,
+            "globalThis.leaked = 42; console.log('set');",
+        )
+        .await
+        .expect("Failed to run JavaScript");
+    assert!(output.console.contains("set"));
+    assert_eq!(sandbox.guests_prepared(), 1);
+
+    let output = sandbox
+        .run_source(
+            WorkloadType::JavaScript,
+            "console.log(typeof globalThis.leaked);",
+        )
+        .await
+        .expect("Failed to run JavaScript on the reused guest");
+    assert_eq!(sandbox.guests_prepared(), 1);
+    // Globals of one run are gone in the next
+    assert!(output.console.contains("undefined"), "{}", output.console);
+
+    // A failed run tears its guest down and the next run still works
+    let cancel_handle = sandbox.cancel_handle();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        cancel_handle.cancel();
+    });
+    let error = sandbox
+        .run("guest-examples/infinite_loop.js")
+        .await
+        .expect_err("Runaway guest should be cancelled");
+    assert!(matches!(error, Error::Cancelled));
+    let output = sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Failed to run JavaScript after a failed run");
+    assert!(output.console.contains("Hello from JavaScript in Nanvix!"));
+
+    // As does a run after shutdown
+    sandbox.shutdown();
+    let output = sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Failed to run JavaScript after shutdown");
+    assert!(output.console.contains("Hello from JavaScript in Nanvix!"));
+}
+
+#[tokio::test]
+async fn test_sandbox_pool_reuses_warm_guests() {
+    let pool_config = PoolConfig::new()
+        .with_size(1)
+        .with_workload_types(vec![WorkloadType::JavaScript]);
+    let pool = SandboxPool::new(RuntimeConfig::new(), pool_config).expect("Failed to create pool");
+    pool.warm().await.expect("Failed to warm pool");
+    assert_eq!(pool.idle_count(), 1);
+    assert_eq!(pool.guests_prepared(), 1);
+
+    // The run takes the warm guest instead of preparing a new one
+    let output = pool
+        .run_source(
+            WorkloadType::JavaScript,
+            "globalThis.leaked = 42; console.log('set');",
+        )
+        .await
+        .expect("Failed to run JavaScript");
+    assert!(output.console.contains("set"));
+    assert_eq!(pool.guests_prepared(), 1);
+    assert_eq!(pool.idle_count(), 1);
+
+    // Globals of one run are gone in the next
+    let output = pool
+        .run_source(
+            WorkloadType::JavaScript,
+            "console.log(typeof globalThis.leaked);",
+        )
+        .await
+        .expect("Failed to run JavaScript on the reused guest");
+    assert!(output.console.contains("undefined"), "{}", output.console);
+
+    // A failed run does not leave the pool without a usable guest
+    let cancel_handle = pool.cancel_handle();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        cancel_handle.cancel();
+    });
+    let error = pool
+        .run("guest-examples/infinite_loop.js")
+        .await
+        .expect_err("Runaway guest should be cancelled");
+    assert!(matches!(error, Error::Cancelled));
+    let output = pool
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Failed to run JavaScript after a failed run");
+    assert!(output.console.contains("Hello from JavaScript in Nanvix!"));
+
+    pool.shutdown();
+    assert_eq!(pool.idle_count(), 0);
+}
+
+#[tokio::test]
+async fn test_sandbox_pool_replaces_guest_after_max_reuse() {
+    let pool_config = PoolConfig::new()
+        .with_size(1)
+        .with_max_reuse(2)
+        .with_workload_types(vec![WorkloadType::JavaScript]);
+    let pool = SandboxPool::new(RuntimeConfig::new(), pool_config).expect("Failed to create pool");
+    pool.warm().await.expect("Failed to warm pool");
+    assert_eq!(pool.guests_prepared(), 1);
+
+    // The warm-up run does not count, so the first run hands the guest back
+    pool.run("guest-examples/hello.js")
+        .await
+        .expect("Failed to run JavaScript");
+    assert_eq!(pool.idle_count(), 1);
+    assert_eq!(pool.guests_prepared(), 1);
+
+    // The second run reaches max_reuse and the guest is replaced
+    pool.run("guest-examples/hello.js")
+        .await
+        .expect("Failed to run JavaScript");
+    let start = Instant::now();
+    while pool.idle_count() == 0 && start.elapsed() < std::time::Duration::from_secs(60) {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(pool.idle_count(), 1);
+    assert_eq!(pool.guests_prepared(), 2);
+
+    pool.shutdown();
+}
+
 #[tokio::test]
 async fn test_syscall_interception() {
     use hyperlight_nanvix::{SyscallAction, SyscallTable};
@@ -80,11 +474,7 @@ async fn test_invalid_workload_type() {
 
     let result = sandbox.run("guest-examples/nonexistent.unknown").await;
 
-    assert!(result.is_err());
-    let error = result.unwrap_err();
-    assert!(error
-        .to_string()
-        .contains("Could not determine workload type"));
+    assert!(matches!(result, Err(Error::UnknownWorkloadType(_))));
 }
 
 #[tokio::test]
@@ -94,11 +484,23 @@ async fn test_nonexistent_file() {
 
     let result = sandbox.run("truly_nonexistent_file_12345.js").await;
     assert!(
-        result.is_err(),
-        "Running a nonexistent file should return an error"
+        matches!(result, Err(Error::WorkloadNotFound(_))),
+        "Running a nonexistent file should return WorkloadNotFound"
     );
 }
 
+#[tokio::test]
+async fn test_offline_mode_uses_cache() {
+    // Relies on the registry having been set up, like the other integration tests
+    let config = RuntimeConfig::new().with_offline(true);
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+
+    sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Offline run should use the cached binaries");
+}
+
 #[tokio::test]
 async fn test_runtime_config_customization() {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -119,3 +521,89 @@ async fn test_runtime_config_customization() {
         .await
         .expect("Failed with custom config");
 }
+
+#[tokio::test]
+async fn test_snapshot_restore_skips_boot() {
+    let snapshot_directory = std::env::temp_dir().join(format!(
+        "hyperlight-nanvix-snapshots-{}",
+        std::process::id()
+    ));
+    let config = RuntimeConfig::new()
+        .with_snapshot_directory(snapshot_directory.to_string_lossy().into_owned());
+
+    // Without a snapshot the first run boots the kernel and starts the interpreter
+    let mut sandbox = Sandbox::new(config.clone()).expect("Failed to create sandbox");
+    sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Failed to run JavaScript");
+    assert_eq!(sandbox.snapshots_restored(), 0);
+    sandbox
+        .snapshot(WorkloadType::JavaScript)
+        .await
+        .expect("Failed to take snapshot");
+    sandbox.shutdown();
+
+    // A fresh sandbox restores the snapshot for its first run instead
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+    let output = sandbox
+        .run("guest-examples/hello.js")
+        .await
+        .expect("Failed to run JavaScript from snapshot");
+    assert!(output.console.contains("Hello from JavaScript in Nanvix!"));
+    assert_eq!(sandbox.snapshots_restored(), 1);
+
+    std::fs::remove_dir_all(&snapshot_directory).ok();
+}
+
+#[tokio::test]
+async fn test_recorded_run_replays() {
+    use hyperlight_nanvix::SyscallTrace;
+
+    let config = RuntimeConfig::new().with_syscall_recording(true);
+    let log_directory = config.log_directory.clone();
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+    let recorded = sandbox
+        .run_with_args("guest-examples/file_ops.js", &["--verbose"])
+        .await
+        .expect("Failed to record JavaScript");
+    let trace_path = recorded.syscall_trace.expect("No syscall trace written");
+    assert!(trace_path.starts_with(&log_directory));
+
+    let trace = SyscallTrace::load(&trace_path).expect("Failed to load trace");
+    assert_eq!(trace.header.args, vec!["--verbose".to_string()]);
+    assert!(trace
+        .records
+        .iter()
+        .all(|record| record.event.syscall == "openat"));
+
+    // The replay gets the recorded outcomes and produces the same output
+    let mut sandbox = Sandbox::new(RuntimeConfig::new().with_syscall_replay(trace))
+        .expect("Failed to create sandbox");
+    let replayed = sandbox
+        .run_with_args("guest-examples/file_ops.js", &["--verbose"])
+        .await
+        .expect("Failed to replay JavaScript");
+    assert_eq!(replayed.console, recorded.console);
+}
+
+#[tokio::test]
+async fn test_injected_fault_reaches_guest() {
+    let fault = "openat:EACCES:path=/tmp/test_file.txt"
+        .parse()
+        .expect("Failed to parse fault");
+    let mut sandbox =
+        Sandbox::new(RuntimeConfig::new().with_fault(fault)).expect("Failed to create sandbox");
+
+    let output = sandbox
+        .run("guest-examples/file_ops.js")
+        .await
+        .expect("Failed to run JavaScript");
+    assert!(output.console.contains("Failed to open file for writing"));
+    assert!(output.console.contains("Failed to open file for reading"));
+    assert!(output.console.contains("File operations test completed!"));
+
+    // Faults on syscalls the host cannot intercept are rejected up front
+    let read_fault = "read:EIO".parse::<hyperlight_nanvix::Fault>();
+    assert!(matches!(read_fault, Err(Error::InvalidFault(_))));
+}
@@ -0,0 +1,28 @@
+//! The memory and CPU-time limits watch the whole host process, so they are
+//! tested in a binary of their own: tests running in parallel in the same
+//! process would count against the budget.
+
+use hyperlight_nanvix::{Error, Limit, RuntimeConfig, Sandbox};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_process_cpu_time_limit_stops_runaway_guest() {
+    let budget = Duration::from_secs(1);
+    let config = RuntimeConfig::new()
+        .with_process_cpu_time_limit(budget)
+        .with_timeout(Duration::from_secs(60));
+    let mut sandbox = Sandbox::new(config).expect("Failed to create sandbox");
+
+    let start = Instant::now();
+    let error = sandbox
+        .run("guest-examples/infinite_loop.js")
+        .await
+        .expect_err("Runaway guest should exceed its CPU-time limit");
+    assert!(
+        matches!(error, Error::LimitExceeded(Limit::ProcessCpuTime(limit)) if limit == budget),
+        "unexpected error: {}",
+        error
+    );
+    // The budget can only be used up by running for at least as long
+    assert!(start.elapsed() >= budget);
+}